@@ -0,0 +1,516 @@
+// acroform.rs
+
+use std::collections::BTreeSet;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+#[cfg(not(target_arch = "wasm32"))]
+use pdfium_render::prelude::Pdfium;
+use pdfium_render::prelude::{
+    PdfDocument, PdfFormField, PdfFormFieldCommon as _, PdfPageAnnotationCommon as _, PdfiumError,
+};
+
+use crate::extraction;
+use crate::field_naming;
+use crate::layout::{Layout, LayoutPage};
+use crate::pdf_objects::{ObjectId, PdfDictionary, PdfObject, PdfSyntaxError, PdfUpdate};
+use crate::pdf_text_input::{PdfInputFieldKind, PdfInputFieldSerde};
+
+/// Field flag bits of interactive pdf forms.
+pub const FLAG_REQUIRED: i64 = 1 << 1;
+pub const FLAG_PUSH_BUTTON: i64 = 1 << 16;
+pub const FLAG_COMB: i64 = 1 << 24;
+/// Annotation flag to print the widget.
+const ANNOTATION_PRINT: i64 = 1 << 2;
+/// Default appearance with automatic font size.
+const TEXT_APPEARANCE: &str = "/Helv 0 Tf 0 g";
+const CHECK_APPEARANCE: &str = "/ZaDb 0 Tf 0 g";
+/// Check mark glyph of the zapf dingbats font and its width in em.
+const CHECK_GLYPH: &str = "4";
+const CHECK_GLYPH_WIDTH: f32 = 0.846;
+
+#[derive(Debug)]
+pub enum FormExportError {
+    PdfError(PdfiumError),
+    Syntax(PdfSyntaxError),
+    Encrypted,
+    /// The document and its copy saved by pdfium index their objects with a cross-reference
+    /// stream, which cannot be extended.
+    CrossReferenceStream,
+    /// The layout has fields on a page the document does not have, `page` starts at 1.
+    MissingPage {
+        page: usize,
+    },
+}
+
+impl fmt::Display for FormExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PdfError(e) => write!(f, "Pdf error: {e}"),
+            Self::Syntax(e) => write!(f, "Could not read pdf structure: {e}"),
+            Self::Encrypted => write!(f, "Encrypted documents are not supported."),
+            Self::CrossReferenceStream => write!(
+                f,
+                "Documents with compressed cross-reference streams are not supported. \
+                 Save the pdf without object streams, e.g. with \
+                 'qpdf --object-streams=disable', and try again."
+            ),
+            Self::MissingPage { page } => write!(f, "page {page} is missing"),
+        }
+    }
+}
+
+/// Reads the widgets of all interactive form fields into a layout.
+///
+/// Push buttons and signature fields have no value to fill in and are skipped. Radio buttons
+/// become checkboxes named after their group and their position in it, e.g. `gender_2`.
+pub fn read_form_fields(pdf_document: &PdfDocument<'_>) -> Result<Layout, PdfiumError> {
+    let mut pages = Vec::with_capacity(pdf_document.pages().len() as usize);
+    for page in pdf_document.pages().iter() {
+        let width = page.width().value;
+        let height = page.height().value;
+        let mut fields = Vec::new();
+        for annotation in page.annotations().iter() {
+            let Some(form_field) = annotation.as_form_field() else {
+                continue;
+            };
+            let bounds = annotation.bounds()?;
+            let mut unique_id = form_field.name().unwrap_or_default();
+            let (kind, text) = match form_field {
+                PdfFormField::Text(text_field) => {
+                    let kind = if text_field.is_combed() {
+                        // the maximum length is not exposed by pdfium, assume square cells
+                        let cells = bounds.width().value / bounds.height().value;
+                        PdfInputFieldKind::Comb {
+                            cells: cells.round().max(1.) as u32,
+                        }
+                    } else {
+                        PdfInputFieldKind::Text
+                    };
+                    (kind, text_field.value().unwrap_or_default())
+                }
+                PdfFormField::Checkbox(checkbox) => {
+                    let checked = checkbox.is_checked().unwrap_or(false);
+                    (PdfInputFieldKind::Checkbox, checked_text(checked))
+                }
+                PdfFormField::RadioButton(radio_button) => {
+                    // the buttons of a group share its name, each becomes a checkbox of its own
+                    unique_id = format!("{unique_id}_{}", radio_button.index_in_group() + 1);
+                    let checked = radio_button.is_checked().unwrap_or(false);
+                    (PdfInputFieldKind::Checkbox, checked_text(checked))
+                }
+                PdfFormField::ComboBox(combo_box) => (
+                    PdfInputFieldKind::Text,
+                    combo_box.value().unwrap_or_default(),
+                ),
+                PdfFormField::ListBox(list_box) => (
+                    PdfInputFieldKind::Text,
+                    list_box.value().unwrap_or_default(),
+                ),
+                PdfFormField::Unknown(_) => (PdfInputFieldKind::Text, String::new()),
+                PdfFormField::PushButton(_) | PdfFormField::Signature(_) => continue,
+            };
+            fields.push(PdfInputFieldSerde {
+                unique_id,
+                pos_x: bounds.left().value,
+                pos_y: height - bounds.top().value,
+                width: bounds.width().value,
+                height: bounds.height().value,
+                text,
+                required: form_field.is_required(),
+                kind,
+                anchor: None,
+            });
+        }
+        pages.push(LayoutPage {
+            width,
+            height,
+            fields,
+        });
+    }
+    Ok(Layout { pages })
+}
+
+fn checked_text(checked: bool) -> String {
+    if checked { "x" } else { "" }.to_owned()
+}
+
+/// Creates a copy of the source document with every field of `layout` as a fillable form field.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn create_fillable_pdf(
+    pdfium: &Pdfium,
+    source_path: &Path,
+    layout: &Layout,
+) -> Result<Vec<u8>, FormExportError> {
+    // pdfium usually saves a classic cross-reference table even for sources with streams
+    let bytes = pdfium
+        .load_pdf_from_file(source_path, None)
+        .and_then(|document| document.save_to_bytes())
+        .map_err(FormExportError::PdfError)?;
+    match add_form_fields(&bytes, layout) {
+        // the source file itself may still have a classic table
+        Err(FormExportError::CrossReferenceStream) => match std::fs::read(source_path) {
+            Ok(source) => add_form_fields(&source, layout),
+            Err(_) => Err(FormExportError::CrossReferenceStream),
+        },
+        result => result,
+    }
+}
+
+/// Appends the fields of `layout` as text, checkbox and comb fields to the pdf file `bytes`.
+///
+/// Fields are named by [`unique_field_name`], so exported fdf and xfdf values match them.
+/// Text fields have no appearance of their own, viewers generate it from the value.
+pub fn add_form_fields(bytes: &[u8], layout: &Layout) -> Result<Vec<u8>, FormExportError> {
+    let mut update = PdfUpdate::new(bytes).map_err(|e| {
+        if e.is_cross_reference_stream() {
+            FormExportError::CrossReferenceStream
+        } else {
+            FormExportError::Syntax(e)
+        }
+    })?;
+    if update.trailer().get("Encrypt").is_some() {
+        return Err(FormExportError::Encrypted);
+    }
+    let page_ids = update.page_ids().map_err(FormExportError::Syntax)?;
+    let helvetica = update.add(font_dictionary("Helvetica"));
+    let zapf_dingbats = update.add(font_dictionary("ZapfDingbats"));
+
+    let mut used_names = existing_field_names(&update).map_err(FormExportError::Syntax)?;
+    let mut fields = Vec::new();
+    for (page_index, layout_page) in layout.pages.iter().enumerate() {
+        if layout_page.fields.is_empty() {
+            continue;
+        }
+        let &page_id = page_ids
+            .get(page_index)
+            .ok_or(FormExportError::MissingPage {
+                page: page_index + 1,
+            })?;
+        let mut widgets = Vec::with_capacity(layout_page.fields.len());
+        for field in &layout_page.fields {
+            let name = unique_field_name(&mut used_names, &field.unique_id);
+            let widget = widget_dictionary(
+                &mut update,
+                field,
+                &name,
+                page_id,
+                layout_page.height,
+                zapf_dingbats,
+            );
+            widgets.push(PdfObject::Reference(
+                update.add(PdfObject::Dictionary(widget)),
+            ));
+        }
+        fields.extend(widgets.iter().cloned());
+        let mut page = update
+            .resolve_dictionary(&PdfObject::Reference(page_id))
+            .map_err(FormExportError::Syntax)?;
+        append_to_array(&mut update, &mut page, "Annots", widgets)?;
+        update.replace(page_id, PdfObject::Dictionary(page));
+    }
+
+    let Some(&PdfObject::Reference(root_id)) = update.trailer().get("Root") else {
+        return Err(FormExportError::Syntax(PdfSyntaxError::malformed(
+            0,
+            "trailer has no catalog",
+        )));
+    };
+    let mut catalog = update
+        .resolve_dictionary(&PdfObject::Reference(root_id))
+        .map_err(FormExportError::Syntax)?;
+    let acro_form_id = match catalog.get("AcroForm") {
+        Some(&PdfObject::Reference(id)) => Some(id),
+        _ => None,
+    };
+    let mut acro_form = match catalog.get("AcroForm") {
+        Some(acro_form) => update
+            .resolve_dictionary(acro_form)
+            .map_err(FormExportError::Syntax)?,
+        None => PdfDictionary::new(),
+    };
+    append_to_array(&mut update, &mut acro_form, "Fields", fields)?;
+    acro_form.insert("NeedAppearances", PdfObject::Bool(true));
+    if acro_form.get("DA").is_none() {
+        acro_form.insert("DA", PdfObject::text(TEXT_APPEARANCE));
+    }
+    let mut resources = match acro_form.get("DR") {
+        Some(resources) => update
+            .resolve_dictionary(resources)
+            .map_err(FormExportError::Syntax)?,
+        None => PdfDictionary::new(),
+    };
+    let mut fonts = match resources.get("Font") {
+        Some(fonts) => update
+            .resolve_dictionary(fonts)
+            .map_err(FormExportError::Syntax)?,
+        None => PdfDictionary::new(),
+    };
+    fonts.insert("Helv", PdfObject::Reference(helvetica));
+    fonts.insert("ZaDb", PdfObject::Reference(zapf_dingbats));
+    resources.insert("Font", PdfObject::Dictionary(fonts));
+    acro_form.insert("DR", PdfObject::Dictionary(resources));
+    if let Some(id) = acro_form_id {
+        update.replace(id, PdfObject::Dictionary(acro_form));
+    } else {
+        catalog.insert("AcroForm", PdfObject::Dictionary(acro_form));
+        update.replace(root_id, PdfObject::Dictionary(catalog));
+    }
+    Ok(update.write())
+}
+
+fn font_dictionary(base_font: &str) -> PdfObject {
+    let mut font = PdfDictionary::new()
+        .with("Type", PdfObject::name("Font"))
+        .with("Subtype", PdfObject::name("Type1"))
+        .with("BaseFont", PdfObject::name(base_font));
+    if base_font == "Helvetica" {
+        font.insert("Encoding", PdfObject::name("WinAnsiEncoding"));
+    }
+    PdfObject::Dictionary(font)
+}
+
+/// Names of the top level fields the document already has.
+fn existing_field_names(update: &PdfUpdate<'_>) -> Result<BTreeSet<String>, PdfSyntaxError> {
+    let mut names = BTreeSet::new();
+    let catalog =
+        update.resolve_dictionary(update.trailer().get("Root").unwrap_or(&PdfObject::Null))?;
+    let Some(acro_form) = catalog.get("AcroForm") else {
+        return Ok(names);
+    };
+    let acro_form = update.resolve_dictionary(acro_form)?;
+    if let Some(fields) = acro_form.get("Fields")
+        && let PdfObject::Array(fields) = update.resolve(fields)?
+    {
+        for field in &fields {
+            if let Some(PdfObject::String(name)) = update.resolve_dictionary(field)?.get("T") {
+                names.insert(String::from_utf8_lossy(name).into_owned());
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Name of the pdf form field for `unique_id`.
+///
+/// Periods separate parent and child field names in pdf, so they are replaced.
+pub fn field_name(unique_id: &str) -> String {
+    unique_id.replace('.', "_")
+}
+
+/// Name of the pdf form field for a field with `unique_id` which is not in `used_names` yet.
+///
+/// Fields without id are called `field`, taken names get the lowest free number appended,
+/// e.g. `name_2`. Fillable pdf and fdf exports name the fields of a layout in order with this.
+pub fn unique_field_name(used_names: &mut BTreeSet<String>, unique_id: &str) -> String {
+    let base = if unique_id.is_empty() {
+        "field".to_owned()
+    } else {
+        field_name(unique_id)
+    };
+    field_naming::unique_name(&base, used_names)
+}
+
+fn widget_dictionary(
+    update: &mut PdfUpdate<'_>,
+    field: &PdfInputFieldSerde,
+    name: &str,
+    page_id: ObjectId,
+    page_height: f32,
+    zapf_dingbats: ObjectId,
+) -> PdfDictionary {
+    let rect = extraction::field_pdf_rect(field, page_height);
+    let mut flags = if field.required { FLAG_REQUIRED } else { 0 };
+    let widget = PdfDictionary::new()
+        .with("Type", PdfObject::name("Annot"))
+        .with("Subtype", PdfObject::name("Widget"))
+        .with("P", PdfObject::Reference(page_id))
+        .with(
+            "Rect",
+            PdfObject::Array(
+                [rect.left(), rect.bottom(), rect.right(), rect.top()]
+                    .map(|value| PdfObject::real(value.value))
+                    .to_vec(),
+            ),
+        )
+        .with("F", PdfObject::integer(ANNOTATION_PRINT))
+        .with("T", PdfObject::text(name));
+    let widget = match field.kind {
+        PdfInputFieldKind::Text | PdfInputFieldKind::Comb { .. } => {
+            let mut widget = widget
+                .with("FT", PdfObject::name("Tx"))
+                .with("DA", PdfObject::text(TEXT_APPEARANCE))
+                .with("V", PdfObject::text(&field.text));
+            if let PdfInputFieldKind::Comb { cells } = field.kind {
+                flags |= FLAG_COMB;
+                widget.insert("MaxLen", PdfObject::integer(i64::from(cells)));
+            }
+            widget
+        }
+        PdfInputFieldKind::Checkbox => {
+            let state = if PdfInputFieldKind::is_checked(&field.text) {
+                "Yes"
+            } else {
+                "Off"
+            };
+            let on = checkbox_appearance(update, field, Some(zapf_dingbats));
+            let off = checkbox_appearance(update, field, None);
+            let normal_appearance = PdfDictionary::new()
+                .with("Yes", PdfObject::Reference(on))
+                .with("Off", PdfObject::Reference(off));
+            widget
+                .with("FT", PdfObject::name("Btn"))
+                .with("DA", PdfObject::text(CHECK_APPEARANCE))
+                .with("V", PdfObject::name(state))
+                .with("AS", PdfObject::name(state))
+                .with(
+                    "MK",
+                    PdfObject::Dictionary(
+                        PdfDictionary::new().with("CA", PdfObject::text(CHECK_GLYPH)),
+                    ),
+                )
+                .with(
+                    "AP",
+                    PdfObject::Dictionary(
+                        PdfDictionary::new().with("N", PdfObject::Dictionary(normal_appearance)),
+                    ),
+                )
+        }
+    };
+    widget.with("Ff", PdfObject::integer(flags))
+}
+
+/// Appearance stream of a checkbox, with a centered check mark if `zapf_dingbats` is given.
+fn checkbox_appearance(
+    update: &mut PdfUpdate<'_>,
+    field: &PdfInputFieldSerde,
+    zapf_dingbats: Option<ObjectId>,
+) -> ObjectId {
+    let mut appearance = PdfDictionary::new()
+        .with("Type", PdfObject::name("XObject"))
+        .with("Subtype", PdfObject::name("Form"))
+        .with(
+            "BBox",
+            PdfObject::Array(
+                [0., 0., field.width, field.height]
+                    .map(PdfObject::real)
+                    .to_vec(),
+            ),
+        );
+    let mut content = String::new();
+    if let Some(zapf_dingbats) = zapf_dingbats {
+        let font_size = field.width.min(field.height) * 0.8;
+        let x = (field.width - font_size * CHECK_GLYPH_WIDTH) / 2.;
+        let y = (field.height - font_size * 0.7) / 2.;
+        content =
+            format!("q 0 g BT /ZaDb {font_size:.2} Tf {x:.2} {y:.2} Td ({CHECK_GLYPH}) Tj ET Q");
+        let fonts = PdfDictionary::new().with("ZaDb", PdfObject::Reference(zapf_dingbats));
+        appearance.insert(
+            "Resources",
+            PdfObject::Dictionary(PdfDictionary::new().with("Font", PdfObject::Dictionary(fonts))),
+        );
+    }
+    update.add_stream(appearance, content.into_bytes())
+}
+
+/// Appends `values` to the array `key` of `dictionary`, which may be direct or a reference.
+fn append_to_array(
+    update: &mut PdfUpdate<'_>,
+    dictionary: &mut PdfDictionary,
+    key: &str,
+    values: Vec<PdfObject>,
+) -> Result<(), FormExportError> {
+    let existing = match dictionary.get(key) {
+        Some(object) => update.resolve(object).map_err(FormExportError::Syntax)?,
+        None => PdfObject::Null,
+    };
+    let mut array = match existing {
+        PdfObject::Array(array) => array,
+        _ => Vec::new(),
+    };
+    array.extend(values);
+    match dictionary.get(key) {
+        Some(&PdfObject::Reference(id)) => update.replace(id, PdfObject::Array(array)),
+        _ => dictionary.insert(key, PdfObject::Array(array)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf_objects::tests::minimal_pdf;
+
+    fn field(unique_id: &str, kind: PdfInputFieldKind, text: &str) -> PdfInputFieldSerde {
+        PdfInputFieldSerde {
+            unique_id: unique_id.to_owned(),
+            pos_x: 10.,
+            pos_y: 20.,
+            width: 60.,
+            height: 12.,
+            text: text.to_owned(),
+            kind,
+            ..Default::default()
+        }
+    }
+
+    fn layout() -> Layout {
+        Layout {
+            pages: vec![LayoutPage {
+                width: 200.,
+                height: 100.,
+                fields: vec![
+                    field("name", PdfInputFieldKind::Text, "Ada"),
+                    field("member", PdfInputFieldKind::Checkbox, "x"),
+                    field("zip", PdfInputFieldKind::Comb { cells: 5 }, ""),
+                ],
+            }],
+        }
+    }
+
+    #[test]
+    fn added_fields_are_in_the_written_update() {
+        let written = add_form_fields(&minimal_pdf(), &layout()).unwrap();
+        let update = PdfUpdate::new(&written).unwrap();
+        assert_eq!(
+            existing_field_names(&update).unwrap(),
+            BTreeSet::from(["name".to_owned(), "member".to_owned(), "zip".to_owned()])
+        );
+        let page = update.resolve_dictionary(&PdfObject::Reference(update.page_ids().unwrap()[0]));
+        let Some(PdfObject::Array(annotations)) = page.unwrap().get("Annots").cloned() else {
+            panic!("page has no annotations");
+        };
+        assert_eq!(annotations.len(), 3);
+        let zip = update.resolve_dictionary(&annotations[2]).unwrap();
+        assert_eq!(zip.get("MaxLen"), Some(&PdfObject::integer(5)));
+        // 20 points from the top of a 100 points high page
+        assert_eq!(
+            zip.get("Rect"),
+            Some(&PdfObject::Array(
+                [10, 68, 70, 80].map(PdfObject::integer).to_vec()
+            ))
+        );
+    }
+
+    #[test]
+    #[ignore = "needs the pdfium library"]
+    fn written_fields_reopen_in_pdfium() {
+        let written = add_form_fields(&minimal_pdf(), &layout()).unwrap();
+        let pdfium = Pdfium::default();
+        let document = pdfium.load_pdf_from_byte_slice(&written, None).unwrap();
+        let read = read_form_fields(&document).unwrap();
+        let fields: Vec<_> = read
+            .fields()
+            .map(|field| (field.unique_id.as_str(), field.kind, field.text.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("name", PdfInputFieldKind::Text, "Ada"),
+                ("member", PdfInputFieldKind::Checkbox, "x"),
+                ("zip", PdfInputFieldKind::Comb { cells: 5 }, ""),
+            ]
+        );
+    }
+}
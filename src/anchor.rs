@@ -0,0 +1,211 @@
+// anchor.rs
+
+use std::fmt;
+
+use egui::{Pos2, Rect, pos2};
+use pdfium_render::prelude::{PdfDocument, PdfiumError};
+use serde::{Deserialize, Serialize};
+
+use crate::layout::Layout;
+use crate::text_layer::{self, TextWord};
+
+/// Text on the page a field is positioned relative to, so it follows the text when a form
+/// shifts.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct FieldAnchor {
+    /// One or more words, matched word by word against the text layer of the page.
+    pub text: String,
+    /// Offset in points from the top left corner of the text to the one of the field.
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnchorProblem {
+    Missing,
+    Ambiguous { occurrences: usize },
+}
+
+/// Anchor which could not be resolved unambiguously, `page` starts at 1.
+#[derive(Debug, Clone)]
+pub struct AnchorWarning {
+    pub page: usize,
+    pub unique_id: String,
+    pub text: String,
+    pub problem: AnchorProblem,
+}
+
+impl fmt::Display for AnchorWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.problem {
+            AnchorProblem::Missing => write!(
+                f,
+                "page {}: anchor '{}' of field '{}' was not found, the field keeps its position",
+                self.page, self.text, self.unique_id
+            ),
+            AnchorProblem::Ambiguous { occurrences } => write!(
+                f,
+                "page {}: anchor '{}' of field '{}' occurs {occurrences} times, \
+                 the one nearest to the field's position was used",
+                self.page, self.text, self.unique_id
+            ),
+        }
+    }
+}
+
+/// Bounds of every occurrence of `text` in `words`, words have to follow each other.
+pub fn find_text(words: &[TextWord], text: &str) -> Vec<Rect> {
+    let needle: Vec<&str> = text.split_whitespace().collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    words
+        .windows(needle.len())
+        .filter(|window| {
+            window
+                .iter()
+                .zip(&needle)
+                .all(|(word, expected)| word.text == *expected)
+        })
+        .filter_map(|window| {
+            window
+                .iter()
+                .map(|word| word.rect)
+                .reduce(|a, b| a.union(b))
+        })
+        .collect()
+}
+
+/// Anchors a field at `field_rect` to the occurrence of `text` nearest to it.
+pub fn anchor_field(words: &[TextWord], field_rect: Rect, text: &str) -> Option<FieldAnchor> {
+    let anchor = find_text(words, text).into_iter().min_by(|a, b| {
+        text_layer::rect_distance(*a, field_rect)
+            .total_cmp(&text_layer::rect_distance(*b, field_rect))
+    })?;
+    let offset = field_rect.min - anchor.min;
+    Some(FieldAnchor {
+        text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+        offset_x: offset.x,
+        offset_y: offset.y,
+    })
+}
+
+/// Moves every anchored field of `layout` to its offset from the anchor text in `words`,
+/// the words of each page.
+///
+/// Fields whose anchor is missing keep their position, of several occurrences the one nearest
+/// to where the anchor was when the field was placed is used.
+pub fn resolve_anchors(layout: &mut Layout, words: &[Vec<TextWord>]) -> Vec<AnchorWarning> {
+    let mut warnings = Vec::new();
+    for (page, layout_page) in layout.pages.iter_mut().enumerate() {
+        let page_words = words.get(page).map_or(&[][..], Vec::as_slice);
+        for field in &mut layout_page.fields {
+            let Some(anchor) = &field.anchor else {
+                continue;
+            };
+            let expected = anchor_origin(pos2(field.pos_x, field.pos_y), anchor);
+            let occurrences = find_text(page_words, &anchor.text);
+            let problem = match occurrences.len() {
+                0 => Some(AnchorProblem::Missing),
+                1 => None,
+                occurrences => Some(AnchorProblem::Ambiguous { occurrences }),
+            };
+            if let Some(problem) = problem {
+                warnings.push(AnchorWarning {
+                    page: page + 1,
+                    unique_id: field.unique_id.clone(),
+                    text: anchor.text.clone(),
+                    problem,
+                });
+            }
+            let nearest = occurrences
+                .iter()
+                .map(|rect| rect.min)
+                .min_by(|a, b| a.distance(expected).total_cmp(&b.distance(expected)));
+            if let Some(origin) = nearest {
+                field.pos_x = origin.x + anchor.offset_x;
+                field.pos_y = origin.y + anchor.offset_y;
+            }
+        }
+    }
+    warnings
+}
+
+/// `warnings` as a list for the status message, each on its own line.
+pub fn warning_list(warnings: &[AnchorWarning]) -> String {
+    warnings
+        .iter()
+        .map(|warning| format!("\n- {warning}"))
+        .collect()
+}
+
+/// [`resolve_anchors`] against the text layer of `document`, which is only read if a field
+/// is anchored.
+pub fn resolve_document_anchors(
+    layout: &mut Layout,
+    document: &PdfDocument<'_>,
+) -> Result<Vec<AnchorWarning>, PdfiumError> {
+    if layout.fields().all(|field| field.anchor.is_none()) {
+        return Ok(Vec::new());
+    }
+    let words = text_layer::document_words(document)?;
+    Ok(resolve_anchors(layout, &words))
+}
+
+/// Top left corner of the anchor text a field at `field_min` was placed relative to.
+pub fn anchor_origin(field_min: Pos2, anchor: &FieldAnchor) -> Pos2 {
+    pos2(field_min.x - anchor.offset_x, field_min.y - anchor.offset_y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x: f32, y: f32) -> TextWord {
+        TextWord {
+            text: text.to_owned(),
+            rect: Rect::from_min_size(pos2(x, y), egui::vec2(30.0, 10.0)),
+        }
+    }
+
+    #[test]
+    fn finds_consecutive_words() {
+        let words = [
+            word("Date", 10.0, 10.0),
+            word("of", 45.0, 10.0),
+            word("birth", 80.0, 10.0),
+            word("Date", 10.0, 50.0),
+        ];
+        assert_eq!(
+            find_text(&words, "of  birth"),
+            vec![Rect::from_min_max(pos2(45.0, 10.0), pos2(110.0, 20.0))]
+        );
+        assert_eq!(find_text(&words, "Date").len(), 2);
+    }
+
+    #[test]
+    fn needs_the_words_in_order() {
+        let words = [word("birth", 10.0, 10.0), word("of", 45.0, 10.0)];
+        assert!(find_text(&words, "of birth").is_empty());
+        assert!(find_text(&words, "birth of date").is_empty());
+        assert!(find_text(&words, " ").is_empty());
+    }
+
+    #[test]
+    fn resolves_to_the_nearest_occurrence() {
+        let words = [word("Name", 10.0, 10.0), word("Name", 10.0, 300.0)];
+        let anchor = anchor_field(
+            &words,
+            Rect::from_min_size(pos2(50.0, 305.0), egui::vec2(100.0, 12.0)),
+            "Name",
+        );
+        assert_eq!(
+            anchor,
+            Some(FieldAnchor {
+                text: "Name".to_owned(),
+                offset_x: 40.0,
+                offset_y: 5.0,
+            })
+        );
+    }
+}
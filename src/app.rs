@@ -1,21 +1,57 @@
+use std::collections::BTreeSet;
 use std::path::PathBuf;
 use std::sync::mpsc;
 
 use egui::{Painter, PointerButton, Pos2, Rect, Response, Sense, TextureHandle};
 use image::DynamicImage;
-use pdfium_render::prelude::PdfDocument;
+use pdfium_render::prelude::{PdfDocument, PdfPage};
 use pdfium_render::prelude::{PdfRenderConfig, PdfiumError};
 use slotmap::{DenseSlotMap, new_key_type};
 
-use crate::file_dialog;
-use crate::pdf_text_input::{PdfInputField, PdfInputFieldState};
+use crate::calibration::{self, CalibrationProfile, CalibrationWizard};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::compare::CompareWindow;
+use crate::exporter::ExporterRegistry;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::field_detection::{FieldCandidate, FieldDetectionWindow};
+use crate::fingerprint::Fingerprint;
+use crate::layout::{FieldValues, Layout, LayoutPage};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::layout_library::{self, LayoutLibraryPanel, LayoutMatch};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::mail_merge::MailMergeWindow;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::onion_skin::OnionSkin;
+use crate::page_inspector::{self, PageObjectInfo};
+use crate::pdf_text_input::{PdfInputField, PdfInputFieldKind, PdfInputFieldState};
+use crate::project::{PROJECT_FORMAT_VERSION, Project};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::recovery::{self, Recovery};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::revision_migration::RevisionMigrationWindow;
+use crate::snapping::{self, SnapGuides};
+use crate::text_layer::TextWord;
+use crate::units::{self, CoordinateOrigin, CoordinateUnit};
+use crate::{acroform, anchor, field_naming, file_dialog, text_layer};
 
 pub enum PdfLoadError {
     FileError,
     PdfError((PathBuf, PdfiumError)),
 }
 
-pub type PdfFileLoadType = Result<(PathBuf, Vec<DynamicImage>), PdfLoadError>;
+pub type PdfFileLoadType = Result<(PathBuf, LoadedPdf), PdfLoadError>;
+
+/// Everything read from a pdf document on load, so the document itself can be dropped.
+pub struct LoadedPdf {
+    pub page_images: Vec<DynamicImage>,
+    /// Interactive form fields already contained in the document.
+    pub form_fields: Layout,
+    pub fingerprint: Fingerprint,
+    /// Text layer of each page, to anchor fields to.
+    pub words: Vec<Vec<TextWord>>,
+    pub snap_guides: Vec<SnapGuides>,
+    pub page_objects: Vec<Vec<PageObjectInfo>>,
+}
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -24,12 +60,113 @@ pub struct PdfCoordPickerApp {
     manual_set_file_path: String,
     page_max_width: String,
     page_max_height: String,
+    coordinate_unit: CoordinateUnit,
+    coordinate_origin: CoordinateOrigin,
+    form_name: String,
+    form_revision: String,
     pub pdf_file_path: Option<PathBuf>,
+    /// Project file the current layout was opened from or saved to.
+    pub project_path: Option<PathBuf>,
+    /// State of the last save or open, to show unsaved changes.
+    #[serde(skip)]
+    saved_project: Option<Project>,
+    /// Project as of the last check for changes, see [`Self::check_for_changes`].
+    #[serde(skip)]
+    current_project: Option<Project>,
+    /// Counts the changes of `current_project`, so the autosave writes each state once.
+    #[serde(skip)]
+    project_generation: u64,
+    #[serde(skip)]
+    unsaved_changes: bool,
+    /// Set when the project was replaced without user input, e.g. by a background load.
+    #[serde(skip)]
+    check_changes: bool,
+    #[serde(skip)]
+    window_title: String,
+    /// Fields of the open pdf when the app was closed, restored with it on the next start.
+    session: Option<Session>,
+    /// Session whose pdf is being loaded on startup, with the project file it belongs to.
+    #[serde(skip)]
+    restored_session: Option<(Session, Option<PathBuf>)>,
+    #[serde(skip)]
+    fingerprint: Fingerprint,
+    /// Directory of project files to look for a layout matching each opened pdf.
+    pub layout_library_dir: Option<PathBuf>,
+    /// Library layout similar to the opened pdf, waiting for the user to apply or ignore it.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_layout_match: Option<LayoutMatch>,
+    /// Best match of the opened pdf while the layout library is read on another thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    layout_match_receiver: Option<mpsc::Receiver<Option<LayoutMatch>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub layout_library: LayoutLibraryPanel,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub revision_migration: RevisionMigrationWindow,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub compare: CompareWindow,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub onion_skin: OnionSkin,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub field_detection: FieldDetectionWindow,
     #[serde(skip)]
     pub pdf_page_textures: Option<Vec<PdfPageImage>>,
     #[serde(skip)]
     selected_page_input_id: Option<PdfPageInputId>,
 
+    calibration_profiles: Vec<CalibrationProfile>,
+    active_calibration: Option<String>,
+    #[serde(skip)]
+    calibration_wizard: CalibrationWizard,
+    #[serde(skip)]
+    pub status_message: Option<String>,
+    /// Long running job on another thread, which reports back a status message.
+    #[serde(skip)]
+    pub background_task: Option<mpsc::Receiver<String>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pub mail_merge: MailMergeWindow,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    autosave: recovery::Autosave,
+    /// Unsaved work of a crashed session, waiting for the user to restore or discard it.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    pending_recovery: Option<Recovery>,
+    #[serde(skip)]
+    pub exporters: ExporterRegistry,
+    #[serde(skip)]
+    pub value_records: Vec<FieldValues>,
+    #[serde(skip)]
+    value_record_index: usize,
+    /// Form fields of the loaded document, waiting for the user to import or ignore them.
+    #[serde(skip)]
+    pub pending_form_fields: Option<Layout>,
+    /// Text layer of each page of the loaded document.
+    #[serde(skip)]
+    pub page_words: Vec<Vec<TextWord>>,
+    /// Lines of each page of the loaded document which field edges snap to.
+    #[serde(skip)]
+    pub snap_guides: Vec<SnapGuides>,
+    /// Page objects and annotations of each page, for inspect mode.
+    #[serde(skip)]
+    page_objects: Vec<Vec<PageObjectInfo>>,
+    /// Hovering a page highlights the object under the cursor instead of placing fields.
+    #[serde(skip)]
+    inspect_mode: bool,
+    /// Page and object hovered last in inspect mode.
+    #[serde(skip)]
+    inspected_object: Option<(usize, PageObjectInfo)>,
+    /// Text typed in to anchor the selected field to.
+    #[serde(skip)]
+    anchor_text: String,
+
     pub waiting_for_file: bool,
     #[serde(skip)]
     pub receiver: mpsc::Receiver<PdfFileLoadType>,
@@ -44,9 +181,59 @@ impl Default for PdfCoordPickerApp {
             manual_set_file_path: String::new(),
             page_max_width: String::new(),
             page_max_height: String::new(),
+            coordinate_unit: CoordinateUnit::default(),
+            coordinate_origin: CoordinateOrigin::default(),
+            form_name: String::new(),
+            form_revision: String::new(),
             pdf_file_path: None,
+            project_path: None,
+            saved_project: None,
+            current_project: None,
+            project_generation: 0,
+            unsaved_changes: false,
+            check_changes: false,
+            window_title: String::new(),
+            session: None,
+            restored_session: None,
+            fingerprint: Fingerprint::default(),
+            layout_library_dir: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_layout_match: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            layout_match_receiver: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            layout_library: LayoutLibraryPanel::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            revision_migration: RevisionMigrationWindow::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            compare: CompareWindow::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            onion_skin: OnionSkin::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            field_detection: FieldDetectionWindow::default(),
             pdf_page_textures: None,
             selected_page_input_id: None,
+            calibration_profiles: Vec::new(),
+            active_calibration: None,
+            calibration_wizard: CalibrationWizard::default(),
+            status_message: None,
+            background_task: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            mail_merge: MailMergeWindow::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            autosave: recovery::Autosave::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_recovery: None,
+            exporters: ExporterRegistry::default(),
+            value_records: Vec::new(),
+            value_record_index: 0,
+            pending_form_fields: None,
+            page_words: Vec::new(),
+            snap_guides: Vec::new(),
+            page_objects: Vec::new(),
+            inspect_mode: false,
+            inspected_object: None,
+            anchor_text: String::new(),
             waiting_for_file: false,
             receiver: sc,
             producer: mp,
@@ -62,11 +249,128 @@ impl PdfCoordPickerApp {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if let Some(storage) = cc.storage {
+        let mut app: Self = if let Some(storage) = cc.storage {
             eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
         } else {
             Default::default()
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            app.restore_session();
+            app.pending_recovery = recovery::read_recovery();
         }
+        app
+    }
+
+    /// Starts loading the pdf of the last session, its fields and selection are restored by
+    /// [`Self::apply_restored_session`] when it is loaded.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restore_session(&mut self) {
+        let Some(mut session) = self.session.take() else {
+            return;
+        };
+        if crate::project::migrate(&mut session.project).is_err() {
+            return;
+        }
+        let pdf_path = session.project.pdf_path.clone();
+        if !std::fs::exists(&pdf_path).unwrap_or(false) {
+            self.status_message = Some(format!(
+                "Could not reopen the pdf file='{}'.",
+                pdf_path.to_string_lossy()
+            ));
+            return;
+        }
+        self.restored_session = Some((session, self.project_path.clone()));
+        crate::file_dialog::file_dialog_native::spawn_pdf_load_thread(self, pdf_path);
+    }
+
+    /// Restores the fields and selection of the last session if the pdf just loaded from
+    /// `pdf_path` is its pdf, returns whether it was.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn apply_restored_session(&mut self, pdf_path: &std::path::Path) -> bool {
+        let Some((session, project_path)) = self
+            .restored_session
+            .take()
+            .filter(|(session, _)| session.project.pdf_path == pdf_path)
+        else {
+            return false;
+        };
+        let anchor_warnings = self.apply_project(session.project);
+        self.project_path = project_path;
+        self.status_message = (!anchor_warnings.is_empty())
+            .then(|| format!("Anchor warnings:{}", anchor::warning_list(&anchor_warnings)));
+        if session.saved {
+            self.mark_project_unchanged();
+        }
+        self.selected_page_input_id = session.selected_field.and_then(|(page_id, index)| {
+            let page = self.pdf_page_textures.as_ref()?.get(page_id)?;
+            let (input_field_key, _) = page.input_fields.iter().nth(index)?;
+            Some(PdfPageInputId {
+                page_id,
+                input_field_key,
+            })
+        });
+        true
+    }
+
+    /// Drops the session of the pdf at `pdf_path` after the pdf could not be loaded.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn discard_restored_session(&mut self, pdf_path: &std::path::Path) {
+        if self
+            .restored_session
+            .take_if(|(session, _)| session.project.pdf_path == pdf_path)
+            .is_some()
+        {
+            self.status_message = Some(format!(
+                "Could not reopen the pdf file='{}'.",
+                pdf_path.to_string_lossy()
+            ));
+        }
+    }
+
+    /// Loads the pdf of `project` and applies the project to it, the project counts as unsaved.
+    ///
+    /// Anchors which could not be resolved are reported in the status message.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reopen_project(
+        &mut self,
+        ctx: &egui::Context,
+        project: Project,
+        project_path: Option<PathBuf>,
+    ) -> bool {
+        let pdf_path = project.pdf_path.clone();
+        let result =
+            crate::file_dialog::file_dialog_native::load_pdf_file_from_filesystem(pdf_path.clone());
+        let Ok((pdf_path, loaded_pdf)) = result else {
+            self.status_message = Some(format!(
+                "Could not reopen the pdf file='{}'.",
+                pdf_path.to_string_lossy()
+            ));
+            return false;
+        };
+        self.pdf_file_path = Some(pdf_path);
+        self.init_loaded_pdf(ctx, loaded_pdf);
+        let anchor_warnings = self.apply_project(project);
+        self.status_message = (!anchor_warnings.is_empty())
+            .then(|| format!("Anchor warnings:{}", anchor::warning_list(&anchor_warnings)));
+        self.project_path = project_path;
+        true
+    }
+
+    fn session(&self) -> Option<Session> {
+        let selected_field = self.selected_page_input_id.and_then(|key| {
+            let page = self.pdf_page_textures.as_ref()?.get(key.page_id)?;
+            let index = page
+                .input_fields
+                .keys()
+                .position(|input_field_key| input_field_key == key.input_field_key)?;
+            Some((key.page_id, index))
+        });
+        Some(Session {
+            project: self.project()?,
+            saved: !self.has_unsaved_changes(),
+            selected_field,
+        })
     }
 
     pub fn init_pdf_page_images(
@@ -81,6 +385,284 @@ impl PdfCoordPickerApp {
         }
     }
 
+    /// Called after a document was loaded, asks to import its form fields if it has any.
+    pub fn init_loaded_pdf(&mut self, ctx: &egui::Context, loaded_pdf: LoadedPdf) {
+        self.init_pdf_page_images(ctx, loaded_pdf.page_images);
+        self.fingerprint = loaded_pdf.fingerprint;
+        self.page_words = loaded_pdf.words;
+        self.snap_guides = loaded_pdf.snap_guides;
+        self.page_objects = loaded_pdf.page_objects;
+        self.inspected_object = None;
+        self.pending_form_fields = if loaded_pdf.form_fields.fields().next().is_some() {
+            Some(loaded_pdf.form_fields)
+        } else {
+            None
+        };
+        self.project_path = None;
+        self.mark_project_unchanged();
+    }
+
+    /// Looks for a layout of the open pdf in the layout library on another thread, see
+    /// [`Self::poll_layout_match`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn match_layout_library(&mut self, ctx: &egui::Context) {
+        self.pending_layout_match = None;
+        self.layout_match_receiver = None;
+        let Some(directory) = self.layout_library_dir.clone() else {
+            return;
+        };
+        let fingerprint = self.fingerprint.clone();
+        let (producer, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            producer
+                .send(layout_library::best_match(&directory, &fingerprint))
+                .ok();
+            ctx.request_repaint();
+        });
+        self.layout_match_receiver = Some(receiver);
+    }
+
+    /// Applies the layout found for the open pdf right away if it was made for the identical
+    /// file and suggests it otherwise.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_layout_match(&mut self) {
+        let Some(receiver) = &self.layout_match_receiver else {
+            return;
+        };
+        let layout_match = match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => None,
+            Ok(layout_match) => layout_match,
+        };
+        self.layout_match_receiver = None;
+        let Some(layout_match) = layout_match else {
+            return;
+        };
+        if layout_match.score >= 1.0 {
+            self.apply_library_layout(layout_match.entry);
+        } else {
+            self.pending_layout_match = Some(layout_match);
+        }
+    }
+
+    /// Applies a library layout as a template, saving does not overwrite the library file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn apply_library_layout(&mut self, entry: layout_library::LibraryEntry) {
+        let name = entry.name();
+        let anchor_warnings = self.apply_project(entry.project);
+        let mut message = format!("Applied layout '{name}' from the layout library.");
+        if !anchor_warnings.is_empty() {
+            message += &format!(
+                "\nAnchor warnings:{}",
+                anchor::warning_list(&anchor_warnings)
+            );
+        }
+        self.status_message = Some(message);
+    }
+
+    /// Current layout and settings, without the hash of the pdf file.
+    pub fn project(&self) -> Option<Project> {
+        Some(Project {
+            version: PROJECT_FORMAT_VERSION,
+            pdf_path: self.pdf_file_path.clone()?,
+            form_name: self.form_name.clone(),
+            revision: self.form_revision.clone(),
+            pdf_hash: String::new(),
+            fingerprint: self.fingerprint.clone(),
+            layout: self.layout(),
+            unit: self.coordinate_unit,
+            origin: self.coordinate_origin,
+            calibration: self.active_calibration().cloned(),
+            page_max_width: self.page_max_width.clone(),
+            page_max_height: self.page_max_height.clone(),
+        })
+    }
+
+    /// Replaces the fields and settings with the ones of `project`, its pdf has to be loaded.
+    ///
+    /// Returns the anchors which could not be resolved.
+    pub fn apply_project(&mut self, project: Project) -> Vec<anchor::AnchorWarning> {
+        for page in self.pdf_page_textures.iter_mut().flatten() {
+            page.input_fields.clear();
+        }
+        self.selected_page_input_id = None;
+        self.pending_form_fields = None;
+        let (_, anchor_warnings) = self.add_input_fields(&project.layout);
+        self.form_name = project.form_name;
+        self.form_revision = project.revision;
+        self.coordinate_unit = project.unit;
+        self.coordinate_origin = project.origin;
+        self.page_max_width = project.page_max_width;
+        self.page_max_height = project.page_max_height;
+        self.active_calibration = project.calibration.map(|profile| {
+            let name = profile.name.clone();
+            match self
+                .calibration_profiles
+                .iter_mut()
+                .find(|existing| existing.name == name)
+            {
+                Some(existing) => *existing = profile,
+                None => self.calibration_profiles.push(profile),
+            }
+            name
+        });
+        anchor_warnings
+    }
+
+    pub fn mark_project_saved(&mut self, project_path: PathBuf) {
+        self.project_path = Some(project_path);
+        self.mark_project_unchanged();
+    }
+
+    /// Takes the current project as the saved one.
+    fn mark_project_unchanged(&mut self) {
+        self.saved_project = self.project();
+        self.check_changes = true;
+    }
+
+    /// Whether the project differs from the saved one as of the last [`Self::check_for_changes`].
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.unsaved_changes
+    }
+
+    /// Compares the project with the saved one, only in frames with input or after the project
+    /// was replaced, as other frames cannot change it.
+    fn check_for_changes(&mut self, ctx: &egui::Context) {
+        let had_input = ctx.input(|i| !i.events.is_empty() || i.pointer.any_down());
+        if !had_input && !self.check_changes {
+            return;
+        }
+        self.check_changes = false;
+        let project = self.project();
+        if project != self.current_project {
+            self.current_project = project;
+            self.project_generation += 1;
+            // let the autosave see the change even if no further input follows
+            ctx.request_repaint();
+        }
+        self.unsaved_changes = self.current_project != self.saved_project;
+    }
+
+    /// Adds the fields of `layout`, anchored ones are moved to their anchor text on the page.
+    ///
+    /// Returns the number of fields added and the anchors which could not be resolved.
+    pub fn add_input_fields(&mut self, layout: &Layout) -> (usize, Vec<anchor::AnchorWarning>) {
+        self.check_changes = true;
+        let mut layout = layout.clone();
+        let anchor_warnings = anchor::resolve_anchors(&mut layout, &self.page_words);
+        let mut added = 0;
+        let pages = self.pdf_page_textures.iter_mut().flatten();
+        for (page, layout_page) in pages.zip(&layout.pages) {
+            for field in &layout_page.fields {
+                page.input_fields
+                    .insert(PdfInputFieldState::from_serde(field));
+                added += 1;
+            }
+        }
+        (added, anchor_warnings)
+    }
+
+    /// Adds a field for each of the accepted `candidates` of field detection.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn add_detected_fields(&mut self, candidates: &[FieldCandidate]) {
+        let mut added = 0;
+        if let Some(pages) = &mut self.pdf_page_textures {
+            for candidate in candidates {
+                if let Some(page) = pages.get_mut(candidate.page) {
+                    let mut input_field = PdfInputFieldState::new(candidate.rect);
+                    input_field.kind = candidate.kind;
+                    page.input_fields.insert(input_field);
+                    added += 1;
+                }
+            }
+        }
+        self.status_message = Some(format!("Added {added} detected fields."));
+    }
+
+    /// Names every field without `unique_id` after its label, returns the number of fields named.
+    pub fn auto_name_fields(&mut self) -> usize {
+        let Some(pages) = &mut self.pdf_page_textures else {
+            return 0;
+        };
+        let mut used: BTreeSet<String> = pages
+            .iter()
+            .flat_map(|page| page.input_fields.values())
+            .map(|input_field| input_field.unique_id.clone())
+            .filter(|unique_id| !unique_id.is_empty())
+            .collect();
+        let mut named = 0;
+        for (page, words) in pages.iter_mut().zip(&self.page_words) {
+            for input_field in page.input_fields.values_mut() {
+                if !input_field.unique_id.is_empty() {
+                    continue;
+                }
+                if let Some(name) = field_naming::suggest_name(words, input_field.rect) {
+                    input_field.unique_id = field_naming::unique_name(&name, &mut used);
+                    named += 1;
+                }
+            }
+        }
+        named
+    }
+
+    pub fn layout(&self) -> Layout {
+        let pages = self
+            .pdf_page_textures
+            .iter()
+            .flatten()
+            .map(|page| LayoutPage {
+                width: page.width,
+                height: page.height,
+                fields: page
+                    .input_fields
+                    .values()
+                    .map(PdfInputFieldState::to_serde)
+                    .collect(),
+            })
+            .collect();
+        Layout { pages }
+    }
+
+    /// Sets the text of every field whose `unique_id` has a value, returns the number of fields set.
+    pub fn apply_field_values(&mut self, values: &FieldValues) -> usize {
+        let mut applied = 0;
+        for page in self.pdf_page_textures.iter_mut().flatten() {
+            for input_field in page.input_fields.values_mut() {
+                if let Some(value) = values.get(&input_field.unique_id) {
+                    value.clone_into(&mut input_field.text);
+                    applied += 1;
+                }
+            }
+        }
+        applied
+    }
+
+    /// Replaces the loaded value records and fills the fields with the first one.
+    pub fn set_value_records(&mut self, records: Vec<FieldValues>) {
+        self.value_records = records;
+        self.select_value_record(0);
+    }
+
+    fn select_value_record(&mut self, index: usize) {
+        self.value_record_index = index;
+        if let Some(record) = self.value_records.get(index).cloned() {
+            let applied = self.apply_field_values(&record);
+            self.status_message = Some(format!(
+                "Filled {applied} of {} values from record {}.",
+                record.len(),
+                index + 1
+            ));
+        }
+    }
+
+    pub fn active_calibration(&self) -> Option<&CalibrationProfile> {
+        let name = self.active_calibration.as_ref()?;
+        self.calibration_profiles
+            .iter()
+            .find(|profile| &profile.name == name)
+    }
+
     fn get_input_field_mut(&mut self, key: PdfPageInputId) -> Option<&mut PdfInputFieldState> {
         if let Some(pages) = &mut self.pdf_page_textures {
             if let Some(page) = pages.get_mut(key.page_id) {
@@ -114,6 +696,16 @@ impl PdfPageImage {
 
 new_key_type! { struct PdfInputFieldKey; }
 
+/// Open pdf and its fields as they were on shutdown.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+struct Session {
+    project: Project,
+    /// Whether the fields matched the saved project file.
+    saved: bool,
+    /// Page and position of the selected field among the fields of its page.
+    selected_field: Option<(usize, usize)>,
+}
+
 #[derive(Debug, Clone, Copy)]
 struct PdfPageInputId {
     page_id: usize,
@@ -126,14 +718,39 @@ pub fn create_images_from_pdf<'a>(
     let mut images = Vec::with_capacity(pdf_document.pages().len() as usize);
     let mut pages = pdf_document.pages().iter();
     while let Some(page) = pages.next() {
-        match page.render_with_config(&PdfRenderConfig::new()) {
-            Ok(pdfbitmap) => images.push(pdfbitmap.as_image()),
-            Err(e) => return Err(e),
-        }
+        images.push(render_page(&page)?);
     }
     Ok(images)
 }
 
+/// Renders `page` at the resolution the pages are shown with.
+pub fn render_page(page: &PdfPage<'_>) -> Result<DynamicImage, PdfiumError> {
+    page.render_with_config(&PdfRenderConfig::new())
+        .map(|pdfbitmap| pdfbitmap.as_image())
+}
+
+/// Reads the form fields, text and objects of `pdf_document` and renders its pages,
+/// `content_hash` of its file becomes part of the fingerprint.
+pub fn load_pdf_document(
+    pdf_document: PdfDocument<'_>,
+    content_hash: String,
+) -> Result<LoadedPdf, PdfiumError> {
+    let form_fields = acroform::read_form_fields(&pdf_document)?;
+    let fingerprint = Fingerprint::from_document(&pdf_document, content_hash)?;
+    let words = text_layer::document_words(&pdf_document)?;
+    let snap_guides = snapping::document_guides(&pdf_document)?;
+    let page_objects = page_inspector::document_objects(&pdf_document)?;
+    let page_images = create_images_from_pdf(pdf_document)?;
+    Ok(LoadedPdf {
+        page_images,
+        form_fields,
+        fingerprint,
+        words,
+        snap_guides,
+        page_objects,
+    })
+}
+
 fn load_pdf_page_image(
     ctx: &egui::Context,
     pdf_page_images: Vec<DynamicImage>,
@@ -151,7 +768,7 @@ fn load_pdf_page_image(
         .collect())
 }
 
-fn convert_to_color_image(image: DynamicImage) -> egui::ColorImage {
+pub fn convert_to_color_image(image: DynamicImage) -> egui::ColorImage {
     use image::EncodableLayout;
     let color_image = match &image {
         DynamicImage::ImageRgb8(image) => {
@@ -175,9 +792,21 @@ fn convert_to_color_image(image: DynamicImage) -> egui::ColorImage {
 impl eframe::App for PdfCoordPickerApp {
     /// Called by the framework to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // a session still loading is kept for the next start
+        self.session = self.session().or_else(|| {
+            self.restored_session
+                .as_ref()
+                .map(|(session, _)| session.clone())
+        });
         eframe::set_value(storage, eframe::APP_KEY, self);
     }
 
+    /// Called once on shutdown, after [`Self::save`] stored the unsaved work.
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        #[cfg(not(target_arch = "wasm32"))]
+        recovery::remove_recovery();
+    }
+
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
@@ -186,30 +815,63 @@ impl eframe::App for PdfCoordPickerApp {
                 file_dialog::file_dialog_native::handle_open_file_dialog_native(self, ctx, ui);
                 #[cfg(target_arch = "wasm32")]
                 file_dialog::file_dialog_web::handle_open_file_dialog_web(self, ctx, ui);
+                calibration::calibration_menu(
+                    ui,
+                    &mut self.calibration_wizard,
+                    &mut self.calibration_profiles,
+                    &mut self.active_calibration,
+                );
                 ui.add_space(16.0);
 
                 egui::widgets::global_theme_preference_buttons(ui);
+
+                draw_status(self, ui);
             });
         });
 
+        #[cfg(not(target_arch = "wasm32"))]
+        autosave_and_recovery_prompt(self, ctx);
+        draw_form_field_import_prompt(self, ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        draw_layout_match_prompt(self, ctx);
+        self.calibration_wizard.show(
+            ctx,
+            &mut self.calibration_profiles,
+            &mut self.active_calibration,
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        draw_layout_library(self, ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        draw_revision_migration(self, ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.compare.open {
+            self.compare.show(ctx, &self.layout());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.onion_skin.open {
+            self.onion_skin.show(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        draw_field_detection(self, ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.mail_merge.open {
+            let layout = self.layout();
+            let calibration = self.active_calibration().cloned();
+            self.mail_merge.show(
+                ctx,
+                self.pdf_file_path.as_deref(),
+                &layout,
+                calibration.as_ref(),
+            );
+        }
+
         egui::SidePanel::right("right_side_panel")
             .resizable(true)
             .show(ctx, |ui| {
-                if let Some(key) = self.selected_page_input_id {
-                    if let Some(input_field) = self.get_input_field_mut(key) {
-                        ui.label(format!(
-                            "page id: {}; input id: {:?}",
-                            key.page_id.clone(),
-                            key.input_field_key
-                        ));
-                        ui.text_edit_singleline(&mut input_field.text);
-                    } else {
-                        ui.label(format!("page id: {};", key.page_id.clone()));
-                        ui.label("Selected input field does not exist anymore.");
-                    }
-                } else {
-                    ui.label("No input field is selected.");
-                }
+                draw_value_record_navigator(self, ui);
+                draw_field_naming(self, ui);
+                draw_inspected_object(self, ui);
+                draw_selected_input_field(self, ui);
             });
 
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -235,14 +897,7 @@ impl eframe::App for PdfCoordPickerApp {
                 }
             });
 
-            ui.horizontal(|ui| {
-                // custom max width
-                ui.label("max width: ");
-                ui.text_edit_singleline(&mut self.page_max_width);
-                // custom max height
-                ui.label("max height: ");
-                ui.text_edit_singleline(&mut self.page_max_height);
-            });
+            ui.horizontal(|ui| draw_page_settings(self, ui));
 
             ui.separator();
 
@@ -260,6 +915,8 @@ impl eframe::App for PdfCoordPickerApp {
                 egui::warn_if_debug_build(ui);
             });
         });
+
+        update_window_title(self, ctx);
     }
 }
 
@@ -301,41 +958,514 @@ fn draw_pdf_pages(app: &mut PdfCoordPickerApp, ui: &mut egui::Ui) {
                     Sense::click() | Sense::hover(),
                 );
                 image.paint_at(ui, response.rect);
+                #[cfg(not(target_arch = "wasm32"))]
+                app.onion_skin.paint(&painter, response.rect, row);
+                #[cfg(not(target_arch = "wasm32"))]
+                if app.field_detection.open {
+                    app.field_detection.paint(&painter, response.rect, row);
+                }
 
+                // holding alt places edges freely
+                let snap_guides = app
+                    .snap_guides
+                    .get(row)
+                    .filter(|_| !ui.input(|i| i.modifiers.alt));
                 draw_pdf_input_fields(
                     &response,
                     &mut painter,
                     row,
                     &mut page.input_fields,
                     &mut app.selected_page_input_id,
+                    snap_guides,
                     ui,
                 );
 
+                if app.inspect_mode {
+                    inspect_page_object(
+                        &response,
+                        &painter,
+                        row,
+                        &app.page_objects,
+                        &mut app.inspected_object,
+                    );
+                    continue;
+                }
                 let _response = handle_pdf_input_create(
                     response,
                     page,
                     &app.page_max_width,
                     &app.page_max_height,
+                    app.coordinate_unit,
+                    app.coordinate_origin,
                 );
             }
         },
     );
 }
 
+fn draw_page_settings(app: &mut PdfCoordPickerApp, ui: &mut egui::Ui) {
+    // custom max width
+    ui.label("max width: ");
+    ui.text_edit_singleline(&mut app.page_max_width);
+    // custom max height
+    ui.label("max height: ");
+    ui.text_edit_singleline(&mut app.page_max_height);
+    units::coordinate_settings_ui(ui, &mut app.coordinate_unit, &mut app.coordinate_origin);
+    ui.checkbox(&mut app.inspect_mode, "inspect")
+        .on_hover_text("Show the pdf object under the cursor instead of placing fields");
+}
+
+/// Highlights the object under the cursor and remembers it for the side panel.
+fn inspect_page_object(
+    response: &Response,
+    painter: &Painter,
+    page: usize,
+    page_objects: &[Vec<PageObjectInfo>],
+    inspected_object: &mut Option<(usize, PageObjectInfo)>,
+) {
+    let Some(pointer) = response.hover_pos() else {
+        return;
+    };
+    let objects = page_objects.get(page).map_or(&[][..], Vec::as_slice);
+    let point = (pointer - response.rect.min).to_pos2();
+    let Some(object) = page_inspector::object_at(objects, point) else {
+        return;
+    };
+    painter.rect(
+        object.bounds.translate(response.rect.min.to_vec2()),
+        0.0,
+        egui::Color32::from_rgba_unmultiplied(255, 140, 0, 40),
+        egui::Stroke::new(1.5, egui::Color32::from_rgb(255, 140, 0)),
+        egui::StrokeKind::Outside,
+    );
+    *inspected_object = Some((page, object.clone()));
+}
+
+fn draw_inspected_object(app: &PdfCoordPickerApp, ui: &mut egui::Ui) {
+    if !app.inspect_mode {
+        return;
+    }
+    match &app.inspected_object {
+        Some((page, object)) => {
+            let page_height = app
+                .pdf_page_textures
+                .iter()
+                .flatten()
+                .nth(*page)
+                .map_or(0.0, |page| page.height);
+            ui.label(format!("page {}", page + 1));
+            page_inspector::inspector_ui(ui, object, page_height);
+        }
+        None => {
+            ui.label("Hover a page to inspect its objects.");
+        }
+    }
+    ui.separator();
+}
+
+/// Runs after all ui of the frame, so changes made in it are seen right away.
+fn update_window_title(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    app.check_for_changes(ctx);
+    let project_name = app
+        .project_path
+        .as_deref()
+        .and_then(std::path::Path::file_name)
+        .map_or_else(|| "Untitled".into(), |name| name.to_string_lossy());
+    let unsaved = if app.has_unsaved_changes() { "*" } else { "" };
+    let title = format!("{project_name}{unsaved} - PDF Coordinates Picker");
+    if title != app.window_title {
+        ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+        app.window_title = title;
+    }
+}
+
+fn draw_status(app: &mut PdfCoordPickerApp, ui: &mut egui::Ui) {
+    if let Some(receiver) = &app.background_task {
+        match receiver.try_recv() {
+            Err(mpsc::TryRecvError::Empty) => {
+                ui.spinner();
+            }
+            Err(mpsc::TryRecvError::Disconnected) => app.background_task = None,
+            Ok(message) => {
+                app.status_message = Some(message);
+                app.background_task = None;
+            }
+        }
+    }
+    if let Some(status_message) = &app.status_message {
+        ui.add_space(16.0);
+        ui.label(status_message);
+    }
+}
+
+fn draw_selected_input_field(app: &mut PdfCoordPickerApp, ui: &mut egui::Ui) {
+    if let Some(key) = app.selected_page_input_id {
+        let mut anchor_text = std::mem::take(&mut app.anchor_text);
+        let mut anchor_clicked = false;
+        let mut suggest_clicked = false;
+        if let Some(input_field) = app.get_input_field_mut(key) {
+            ui.label(format!(
+                "page id: {}; input id: {:?}",
+                key.page_id.clone(),
+                key.input_field_key
+            ));
+            egui::Grid::new("selected_input_field_grid").show(ui, |ui| {
+                ui.label("id: ");
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut input_field.unique_id);
+                    suggest_clicked = ui
+                        .button("Suggest")
+                        .on_hover_text("Name the field after the nearest label")
+                        .clicked();
+                });
+                ui.end_row();
+                ui.label("text: ");
+                ui.text_edit_singleline(&mut input_field.text);
+                ui.end_row();
+                ui.label("required: ");
+                ui.checkbox(&mut input_field.required, "");
+                ui.end_row();
+                ui.label("kind: ");
+                field_kind_editor(ui, &mut input_field.kind);
+                ui.end_row();
+                ui.label("anchor: ");
+                ui.horizontal(|ui| {
+                    if let Some((text, _)) = &input_field.anchor {
+                        ui.label(format!("'{text}'"));
+                        if ui.button("Remove").clicked() {
+                            input_field.anchor = None;
+                        }
+                    } else {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut anchor_text)
+                                .hint_text("text on the page")
+                                .desired_width(120.0),
+                        );
+                        anchor_clicked = ui
+                            .button("Anchor")
+                            .on_hover_text("Keep the field's offset from the nearest occurrence")
+                            .clicked();
+                    }
+                });
+                ui.end_row();
+            });
+        } else {
+            ui.label(format!("page id: {};", key.page_id.clone()));
+            ui.label("Selected input field does not exist anymore.");
+        }
+        ui.weak("Edges snap to lines and text baselines of the pdf, hold Alt to resize freely.");
+        if anchor_clicked {
+            anchor_input_field(app, key, &anchor_text);
+        }
+        if suggest_clicked {
+            suggest_input_field_name(app, key);
+        }
+        app.anchor_text = anchor_text;
+    } else {
+        ui.label("No input field is selected.");
+    }
+}
+
+/// Names the field after its label, keeping the name unique among the other fields.
+fn suggest_input_field_name(app: &mut PdfCoordPickerApp, key: PdfPageInputId) {
+    let Some(rect) = app.get_input_field_mut(key).map(|input_field| input_field.rect) else {
+        return;
+    };
+    let words = app.page_words.get(key.page_id).map_or(&[][..], Vec::as_slice);
+    let Some(name) = field_naming::suggest_name(words, rect) else {
+        app.status_message = Some("No label was found next to the field.".to_owned());
+        return;
+    };
+    let mut used: BTreeSet<String> = app
+        .layout()
+        .fields()
+        .map(|field| field.unique_id.clone())
+        .collect();
+    if let Some(input_field) = app.get_input_field_mut(key) {
+        // the field's own name does not count as taken
+        used.remove(&input_field.unique_id);
+        input_field.unique_id = field_naming::unique_name(&name, &mut used);
+    }
+}
+
+/// Anchors the field to the occurrence of `text` on its page nearest to it.
+fn anchor_input_field(app: &mut PdfCoordPickerApp, key: PdfPageInputId, text: &str) {
+    let rect = app.get_input_field_mut(key).map(|input_field| input_field.rect);
+    let words = app.page_words.get(key.page_id).map_or(&[][..], Vec::as_slice);
+    let anchor = rect
+        .and_then(|rect| anchor::anchor_field(words, rect, text).map(|anchor| (rect, anchor)));
+    match anchor {
+        Some((rect, field_anchor)) => {
+            if let Some(input_field) = app.get_input_field_mut(key) {
+                let origin = anchor::anchor_origin(rect.min, &field_anchor);
+                input_field.anchor = Some((field_anchor.text, origin));
+            }
+            app.status_message = None;
+        }
+        None => {
+            app.status_message = Some(format!(
+                "The text '{text}' was not found on page {}.",
+                key.page_id + 1
+            ));
+        }
+    }
+}
+
+/// Writes unsaved work to the recovery file and offers to restore the one of a crashed session.
+#[cfg(not(target_arch = "wasm32"))]
+fn autosave_and_recovery_prompt(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    if let Some(pending_recovery) = &app.pending_recovery {
+        let mut restore = None;
+        egui::Window::new("Recover unsaved work")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "The app was not closed properly. Restore the unsaved fields of file='{}'?",
+                    pending_recovery.project.pdf_path.to_string_lossy()
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        restore = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        restore = Some(false);
+                    }
+                });
+            });
+        match restore {
+            Some(true) => {
+                if let Some(recovery) = app.pending_recovery.take() {
+                    app.reopen_project(ctx, recovery.project, recovery.project_path);
+                    app.saved_project = None;
+                    app.check_changes = true;
+                }
+            }
+            Some(false) => {
+                app.pending_recovery = None;
+                recovery::remove_recovery();
+            }
+            None => {}
+        }
+        // keep the recovery file until the user decided
+        return;
+    }
+    let unsaved = app.unsaved_changes.then(|| {
+        let field_count = app
+            .current_project
+            .as_ref()
+            .map_or(0, |project| project.layout.fields().count());
+        (app.project_generation, field_count)
+    });
+    let recovery = || {
+        Some(Recovery {
+            project_path: app.project_path.clone(),
+            project: app.current_project.clone()?,
+        })
+    };
+    let now = ctx.input(|i| i.time);
+    if let Some(due_in) = app.autosave.update(now, unsaved, recovery) {
+        ctx.request_repaint_after_secs(due_in as f32);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn draw_field_detection(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    if app.field_detection.open
+        && let Some(candidates) = app.field_detection.show(ctx)
+    {
+        app.add_detected_fields(&candidates);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn draw_layout_library(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    if !app.layout_library.open {
+        return;
+    }
+    let entry = app.layout_library.show(
+        ctx,
+        app.layout_library_dir.as_deref(),
+        &mut app.form_name,
+        &mut app.form_revision,
+        app.pdf_page_textures.is_some(),
+    );
+    if let Some(entry) = entry {
+        app.apply_library_layout(entry);
+    }
+}
+
+/// Opens the new revision with the migrated layout once the user applies the review.
+#[cfg(not(target_arch = "wasm32"))]
+fn draw_revision_migration(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    if !app.revision_migration.open {
+        return;
+    }
+    let Some((new_pdf_path, migrated)) = app.revision_migration.show(ctx) else {
+        return;
+    };
+    let Some(mut project) = app.project() else {
+        return;
+    };
+    project.pdf_path = new_pdf_path;
+    project.layout = migrated;
+    if app.reopen_project(ctx, project, None) {
+        let anchor_warnings = app.status_message.take();
+        app.status_message = Some(
+            std::iter::once("Opened the new revision, check the migrated fields before saving.")
+                .chain(anchor_warnings.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn draw_layout_match_prompt(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    app.poll_layout_match();
+    let Some(layout_match) = &app.pending_layout_match else {
+        return;
+    };
+    let mut apply = None;
+    egui::Window::new("Matching layout")
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "The layout '{}' of the layout library matches this document by {:.0} %. Apply it?",
+                layout_match.entry.name(),
+                layout_match.score * 100.0
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Apply").clicked() {
+                    apply = Some(true);
+                }
+                if ui.button("Ignore").clicked() {
+                    apply = Some(false);
+                }
+            });
+        });
+    match apply {
+        Some(true) => {
+            if let Some(layout_match) = app.pending_layout_match.take() {
+                app.apply_library_layout(layout_match.entry);
+            }
+        }
+        Some(false) => app.pending_layout_match = None,
+        None => {}
+    }
+}
+
+fn draw_form_field_import_prompt(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    let Some(form_fields) = &app.pending_form_fields else {
+        return;
+    };
+    let mut import = None;
+    egui::Window::new("Form fields")
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "This document contains {} form fields. Import them as input fields?",
+                form_fields.fields().count()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Import").clicked() {
+                    import = Some(true);
+                }
+                if ui.button("Ignore").clicked() {
+                    import = Some(false);
+                }
+            });
+        });
+    match import {
+        Some(true) => {
+            if let Some(form_fields) = app.pending_form_fields.take() {
+                let (added, _) = app.add_input_fields(&form_fields);
+                app.status_message = Some(format!("Imported {added} form fields."));
+            }
+        }
+        Some(false) => app.pending_form_fields = None,
+        None => {}
+    }
+}
+
+fn field_kind_editor(ui: &mut egui::Ui, kind: &mut PdfInputFieldKind) {
+    ui.horizontal(|ui| {
+        let label = match kind {
+            PdfInputFieldKind::Text => "text",
+            PdfInputFieldKind::Checkbox => "checkbox",
+            PdfInputFieldKind::Comb { .. } => "comb",
+        };
+        egui::ComboBox::from_id_salt("input_field_kind")
+            .selected_text(label)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(kind, PdfInputFieldKind::Text, "text");
+                ui.selectable_value(kind, PdfInputFieldKind::Checkbox, "checkbox");
+                if ui
+                    .selectable_label(matches!(kind, PdfInputFieldKind::Comb { .. }), "comb")
+                    .clicked()
+                    && !matches!(kind, PdfInputFieldKind::Comb { .. })
+                {
+                    *kind = PdfInputFieldKind::Comb { cells: 10 };
+                }
+            });
+        if let PdfInputFieldKind::Comb { cells } = kind {
+            ui.add(egui::DragValue::new(cells).range(1..=100).suffix(" cells"));
+        }
+    });
+}
+
+fn draw_field_naming(app: &mut PdfCoordPickerApp, ui: &mut egui::Ui) {
+    if app.pdf_page_textures.is_none() {
+        return;
+    }
+    if ui
+        .button("Auto-name unnamed fields")
+        .on_hover_text("Name fields after the nearest label left of or above them")
+        .clicked()
+    {
+        let named = app.auto_name_fields();
+        app.status_message = Some(format!("Named {named} fields after their labels."));
+    }
+    ui.separator();
+}
+
+fn draw_value_record_navigator(app: &mut PdfCoordPickerApp, ui: &mut egui::Ui) {
+    let record_count = app.value_records.len();
+    if record_count <= 1 {
+        return;
+    }
+    ui.horizontal(|ui| {
+        let index = app.value_record_index;
+        if ui.add_enabled(index > 0, egui::Button::new("◀")).clicked() {
+            app.select_value_record(index - 1);
+        }
+        ui.label(format!("record {} of {record_count}", index + 1));
+        if ui
+            .add_enabled(index + 1 < record_count, egui::Button::new("▶"))
+            .clicked()
+        {
+            app.select_value_record(index + 1);
+        }
+    });
+    ui.separator();
+}
+
 fn handle_pdf_input_create(
     mut pdf_page_response: Response,
     pdf_page: &mut PdfPageImage,
     max_width: &str,
     max_height: &str,
+    unit: CoordinateUnit,
+    origin: CoordinateOrigin,
 ) -> Response {
     if let Some(Pos2 { x, y }) = pdf_page_response.interact_pointer_pos() {
-        println!("clicked mouse in rectangle got pos");
         let delta_x = (pdf_page_response.rect.left() - x).abs();
         let delta_y = (pdf_page_response.rect.top() - y).abs();
 
         if pdf_page_response.clicked_by(PointerButton::Primary) {
             let delta_pos = (delta_x, delta_y).into();
-            println!("clicked mouse in rectangle");
             pdf_page
                 .input_fields
                 .insert(PdfInputFieldState::new(Rect::from_center_size(
@@ -353,7 +1483,12 @@ fn handle_pdf_input_create(
         let height_fraction: f32 = max_height / pdf_page.height;
 
         let delta_x = (x - pdf_page_response.rect.left()).abs() * width_fraction;
-        let delta_y = (y - pdf_page_response.rect.top()).abs() * height_fraction;
+        let mut delta_y = (y - pdf_page_response.rect.top()).abs() * height_fraction;
+        if origin == CoordinateOrigin::BottomLeft {
+            delta_y = max_height - delta_y;
+        }
+        let delta_x = unit.convert_points(delta_x);
+        let delta_y = unit.convert_points(delta_y);
         pdf_page_response = pdf_page_response.on_hover_ui_at_pointer(|ui| {
             ui.label(format!("x: {delta_x}; y: {delta_y};"));
         });
@@ -368,11 +1503,13 @@ fn draw_pdf_input_fields(
     page_id: usize,
     pdf_input_fields: &mut DenseSlotMap<PdfInputFieldKey, PdfInputFieldState>,
     selected_input_field_key: &mut Option<PdfPageInputId>,
+    snap_guides: Option<&SnapGuides>,
     ui: &mut egui::Ui,
 ) {
     let mut key_to_remove = None;
     for (key, input_field) in pdf_input_fields.iter_mut() {
-        let input_field_response = PdfInputField::new().show(input_field, response, painter, ui);
+        let input_field_response =
+            PdfInputField::new().show(input_field, response, painter, snap_guides, ui);
         if response.clicked_by(PointerButton::Primary) {
             *selected_input_field_key = Some(PdfPageInputId {
                 page_id,
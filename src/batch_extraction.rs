@@ -0,0 +1,78 @@
+// batch_extraction.rs
+
+use std::path::Path;
+
+use pdfium_render::prelude::Pdfium;
+
+use crate::anchor;
+use crate::csv_table::CsvTable;
+use crate::extraction;
+use crate::layout::Layout;
+use crate::pdf_load;
+
+/// Extracts the fields of `layout` from every pdf file in `directory`.
+///
+/// The table has a `file` column, one column per field and a `warnings` column.
+pub fn extract_directory(directory: &Path, layout: &Layout) -> std::io::Result<CsvTable> {
+    let mut paths: Vec<_> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+        })
+        .collect();
+    paths.sort();
+
+    let mut field_ids: Vec<String> = Vec::new();
+    for field in layout.fields().filter(|field| !field.unique_id.is_empty()) {
+        if !field_ids.contains(&field.unique_id) {
+            field_ids.push(field.unique_id.clone());
+        }
+    }
+    let headers = std::iter::once("file".to_owned())
+        .chain(field_ids.iter().cloned())
+        .chain(std::iter::once("warnings".to_owned()))
+        .collect();
+
+    let pdfium = Pdfium::default();
+    let rows = paths
+        .iter()
+        .map(|path| {
+            let file_name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let extraction = pdf_load::load_pdf_native(&pdfium, path).and_then(|document| {
+                // anchored fields are resolved against the text of each file
+                let mut layout = layout.clone();
+                let anchor_warnings = anchor::resolve_document_anchors(&mut layout, &document)?;
+                extraction::extract_fields(&document, &layout)
+                    .map(|extraction| (extraction, anchor_warnings))
+            });
+            let (mut values, warnings) = match extraction {
+                Ok((extraction, anchor_warnings)) => (
+                    extraction.values,
+                    anchor_warnings
+                        .iter()
+                        .map(ToString::to_string)
+                        .chain(extraction.warnings.iter().map(ToString::to_string))
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                ),
+                Err(e) => (Default::default(), format!("could not load pdf: {e}")),
+            };
+            std::iter::once(file_name)
+                .chain(
+                    field_ids
+                        .iter()
+                        .map(|unique_id| values.remove(unique_id).unwrap_or_default()),
+                )
+                .chain(std::iter::once(warnings))
+                .collect()
+        })
+        .collect();
+    Ok(CsvTable { headers, rows })
+}
@@ -0,0 +1,297 @@
+// calibration.rs
+
+use pdfium_render::prelude::{
+    PdfColor, PdfPage, PdfPageObjectsCommon as _, PdfPagePaperSize, PdfPoints, Pdfium, PdfiumError,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::units::{mm_to_pt, pt_to_mm};
+
+/// Distance of the reference cross on the calibration sheet from the left and top page edge.
+pub const CALIBRATION_MARK_MM: f32 = 30.0;
+
+/// Grid spacing of the calibration sheet.
+const GRID_STEP_MM: usize = 5;
+
+/// Shift a printer applies to its output, measured on a printed calibration sheet.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct CalibrationProfile {
+    pub name: String,
+    /// Positive values mean the printout is shifted to the right.
+    pub offset_x_mm: f32,
+    /// Positive values mean the printout is shifted downwards.
+    pub offset_y_mm: f32,
+}
+
+impl CalibrationProfile {
+    /// Translation in pdf points (bottom left origin) that cancels out the printer shift.
+    pub fn correction_pt(&self) -> (f32, f32) {
+        (-mm_to_pt(self.offset_x_mm), mm_to_pt(self.offset_y_mm))
+    }
+}
+
+/// Creates a single A4 page with a millimetre grid and a reference cross
+/// at [`CALIBRATION_MARK_MM`] from the top left corner.
+pub fn create_calibration_sheet(pdfium: &Pdfium) -> Result<Vec<u8>, PdfiumError> {
+    let mut document = pdfium.create_new_pdf()?;
+    let font = document.fonts_mut().helvetica();
+    let mut page = document
+        .pages_mut()
+        .create_page_at_end(PdfPagePaperSize::a4())?;
+    let width = page.width().value;
+    let height = page.height().value;
+
+    draw_grid(&mut page, width, height)?;
+
+    let mark_x = mm_to_pt(CALIBRATION_MARK_MM);
+    let mark_y = height - mm_to_pt(CALIBRATION_MARK_MM);
+    let cross_size = mm_to_pt(8.);
+    let objects = page.objects_mut();
+    objects.create_path_object_line(
+        PdfPoints::new(mark_x - cross_size),
+        PdfPoints::new(mark_y),
+        PdfPoints::new(mark_x + cross_size),
+        PdfPoints::new(mark_y),
+        PdfColor::RED,
+        PdfPoints::new(0.5),
+    )?;
+    objects.create_path_object_line(
+        PdfPoints::new(mark_x),
+        PdfPoints::new(mark_y - cross_size),
+        PdfPoints::new(mark_x),
+        PdfPoints::new(mark_y + cross_size),
+        PdfColor::RED,
+        PdfPoints::new(0.5),
+    )?;
+
+    let instructions = [
+        "Print this page at 100% scale (no \"fit to page\").".to_owned(),
+        format!(
+            "Measure the distance of the red cross from the left and from the top paper edge in mm (expected: {CALIBRATION_MARK_MM} mm)."
+        ),
+        "Enter both values in the calibration wizard.".to_owned(),
+    ];
+    for (line, text) in instructions.iter().enumerate() {
+        objects.create_text_object(
+            PdfPoints::new(mm_to_pt(50.)),
+            PdfPoints::new(height / 2. - line as f32 * 14.),
+            text,
+            font,
+            PdfPoints::new(10.),
+        )?;
+    }
+
+    document.save_to_bytes()
+}
+
+fn draw_grid(page: &mut PdfPage<'_>, width: f32, height: f32) -> Result<(), PdfiumError> {
+    let objects = page.objects_mut();
+    let grid_color = |mm: usize| {
+        if mm % 10 == 0 {
+            (PdfColor::GREY_50, PdfPoints::new(0.4))
+        } else {
+            (PdfColor::GREY_80, PdfPoints::new(0.2))
+        }
+    };
+
+    for mm in (0..=pt_to_mm(width) as usize).step_by(GRID_STEP_MM) {
+        let x = mm_to_pt(mm as f32);
+        let (color, stroke_width) = grid_color(mm);
+        objects.create_path_object_line(
+            PdfPoints::new(x),
+            PdfPoints::ZERO,
+            PdfPoints::new(x),
+            PdfPoints::new(height),
+            color,
+            stroke_width,
+        )?;
+    }
+    for mm in (0..=pt_to_mm(height) as usize).step_by(GRID_STEP_MM) {
+        let y = height - mm_to_pt(mm as f32);
+        let (color, stroke_width) = grid_color(mm);
+        objects.create_path_object_line(
+            PdfPoints::ZERO,
+            PdfPoints::new(y),
+            PdfPoints::new(width),
+            PdfPoints::new(y),
+            color,
+            stroke_width,
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum CalibrationStep {
+    #[default]
+    PrintSheet,
+    Measure,
+    Save,
+}
+
+/// Window guiding the user from printing a calibration sheet to a stored [`CalibrationProfile`].
+pub struct CalibrationWizard {
+    pub open: bool,
+    step: CalibrationStep,
+    measured_left_mm: f32,
+    measured_top_mm: f32,
+    profile_name: String,
+    status: Option<String>,
+}
+
+impl Default for CalibrationWizard {
+    fn default() -> Self {
+        Self {
+            open: false,
+            step: CalibrationStep::default(),
+            measured_left_mm: CALIBRATION_MARK_MM,
+            measured_top_mm: CALIBRATION_MARK_MM,
+            profile_name: String::new(),
+            status: None,
+        }
+    }
+}
+
+impl CalibrationWizard {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        profiles: &mut Vec<CalibrationProfile>,
+        active_profile: &mut Option<String>,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Printer calibration")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| match self.step {
+                CalibrationStep::PrintSheet => self.ui_print_sheet(ui),
+                CalibrationStep::Measure => self.ui_measure(ui),
+                CalibrationStep::Save => self.ui_save(ui, profiles, active_profile),
+            });
+        self.open = open && self.open;
+    }
+
+    fn ui_print_sheet(&mut self, ui: &mut egui::Ui) {
+        ui.label("1. Save the calibration sheet and print it at 100% scale.");
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Save calibration sheet…").clicked() {
+            self.status = match create_calibration_sheet(&Pdfium::default()) {
+                Ok(bytes) => {
+                    crate::file_dialog::file_dialog_native::spawn_save_file_dialog(
+                        "calibration_sheet.pdf",
+                        bytes,
+                    );
+                    None
+                }
+                Err(e) => Some(format!("Could not create calibration sheet: {e}")),
+            };
+        }
+        #[cfg(target_arch = "wasm32")]
+        ui.label("Saving the calibration sheet is not supported on the web yet.");
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+        ui.separator();
+        if ui.button("Next").clicked() {
+            self.step = CalibrationStep::Measure;
+        }
+    }
+
+    fn ui_measure(&mut self, ui: &mut egui::Ui) {
+        ui.label("2. Measure the position of the red cross on the printed page.");
+        egui::Grid::new("calibration_measure_grid").show(ui, |ui| {
+            ui.label("from left edge (mm): ");
+            ui.add(egui::DragValue::new(&mut self.measured_left_mm).speed(0.1));
+            ui.end_row();
+            ui.label("from top edge (mm): ");
+            ui.add(egui::DragValue::new(&mut self.measured_top_mm).speed(0.1));
+            ui.end_row();
+        });
+        ui.label(format!(
+            "printer offset: x {:+.1} mm; y {:+.1} mm",
+            self.measured_left_mm - CALIBRATION_MARK_MM,
+            self.measured_top_mm - CALIBRATION_MARK_MM
+        ));
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                self.step = CalibrationStep::PrintSheet;
+            }
+            if ui.button("Next").clicked() {
+                self.step = CalibrationStep::Save;
+            }
+        });
+    }
+
+    fn ui_save(
+        &mut self,
+        ui: &mut egui::Ui,
+        profiles: &mut Vec<CalibrationProfile>,
+        active_profile: &mut Option<String>,
+    ) {
+        ui.label("3. Name the calibration profile, e.g. after the printer.");
+        ui.text_edit_singleline(&mut self.profile_name);
+        let name = self.profile_name.trim().to_owned();
+        if profiles.iter().any(|profile| profile.name == name) {
+            ui.label("A profile with this name already exists and will be replaced.");
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Back").clicked() {
+                self.step = CalibrationStep::Measure;
+            }
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save profile"))
+                .clicked()
+            {
+                let profile = CalibrationProfile {
+                    name: name.clone(),
+                    offset_x_mm: self.measured_left_mm - CALIBRATION_MARK_MM,
+                    offset_y_mm: self.measured_top_mm - CALIBRATION_MARK_MM,
+                };
+                profiles.retain(|existing| existing.name != profile.name);
+                *active_profile = Some(profile.name.clone());
+                profiles.push(profile);
+                *self = Self::default();
+            }
+        });
+    }
+}
+
+/// Menu listing the stored calibration profiles; the selected one is applied to exports.
+pub fn calibration_menu(
+    ui: &mut egui::Ui,
+    wizard: &mut CalibrationWizard,
+    profiles: &mut Vec<CalibrationProfile>,
+    active_profile: &mut Option<String>,
+) {
+    ui.menu_button("Calibration", |ui| {
+        if ui.button("Calibration wizard…").clicked() {
+            wizard.open = true;
+        }
+        ui.separator();
+        ui.radio_value(active_profile, None, "No calibration");
+        let mut profile_to_remove = None;
+        for profile in profiles.iter() {
+            ui.horizontal(|ui| {
+                ui.radio_value(
+                    active_profile,
+                    Some(profile.name.clone()),
+                    format!(
+                        "{} ({:+.1} mm, {:+.1} mm)",
+                        profile.name, profile.offset_x_mm, profile.offset_y_mm
+                    ),
+                );
+                if ui.small_button("🗑").clicked() {
+                    profile_to_remove = Some(profile.name.clone());
+                }
+            });
+        }
+        if let Some(name) = profile_to_remove {
+            profiles.retain(|profile| profile.name != name);
+            if active_profile.as_ref() == Some(&name) {
+                *active_profile = None;
+            }
+        }
+    });
+}
@@ -0,0 +1,255 @@
+// code_export.rs
+
+use std::collections::BTreeSet;
+
+use crate::field_naming;
+use crate::layout::Layout;
+use crate::units;
+
+/// Pdf libraries to generate field coordinates for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeTarget {
+    /// Rust `printpdf`, millimeters from the bottom left corner.
+    PrintPdf,
+    /// Rust `lopdf`, points from the bottom left corner as annotation rects.
+    Lopdf,
+    /// Python `reportlab`, points from the bottom left corner.
+    Reportlab,
+    /// JavaScript `pdf-lib`, points from the bottom left corner.
+    PdfLib,
+}
+
+impl CodeTarget {
+    pub const ALL: [Self; 4] = [Self::PrintPdf, Self::Lopdf, Self::Reportlab, Self::PdfLib];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PrintPdf => "Rust printpdf",
+            Self::Lopdf => "Rust lopdf",
+            Self::Reportlab => "Python reportlab",
+            Self::PdfLib => "JavaScript pdf-lib",
+        }
+    }
+
+    pub fn file_extension(self) -> &'static str {
+        match self {
+            Self::PrintPdf | Self::Lopdf => "rs",
+            Self::Reportlab => "py",
+            Self::PdfLib => "js",
+        }
+    }
+
+    pub fn generate(self, layout: &Layout) -> String {
+        let fields = code_fields(layout);
+        match self {
+            Self::PrintPdf => generate_printpdf(&fields),
+            Self::Lopdf => generate_lopdf(&fields),
+            Self::Reportlab => generate_reportlab(&fields),
+            Self::PdfLib => generate_pdf_lib(&fields),
+        }
+    }
+}
+
+/// Field in pdf points with the origin in the bottom left corner of its page.
+struct CodeField {
+    unique_id: String,
+    /// Page index starting at 0.
+    page: usize,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+fn code_fields(layout: &Layout) -> Vec<CodeField> {
+    let mut fields = Vec::new();
+    for (page, layout_page) in layout.pages.iter().enumerate() {
+        for field in &layout_page.fields {
+            fields.push(CodeField {
+                unique_id: field.unique_id.clone(),
+                page,
+                x: field.pos_x,
+                y: layout_page.height - field.pos_y - field.height,
+                width: field.width,
+                height: field.height,
+            });
+        }
+    }
+    fields
+}
+
+/// Unique `SCREAMING_SNAKE_CASE` names for rust constants.
+fn rust_constant_names(fields: &[CodeField]) -> Vec<String> {
+    let mut used_names = BTreeSet::new();
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let mut base: String = field
+                .unique_id
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_uppercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            if base.trim_matches('_').is_empty() {
+                base = format!("FIELD_{}", index + 1);
+            } else if base.starts_with(|c: char| c.is_ascii_digit()) {
+                base.insert(0, '_');
+            }
+            field_naming::unique_name(&base, &mut used_names)
+        })
+        .collect()
+}
+
+/// String literal valid in rust, python and javascript.
+fn quoted(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            // all three languages read two hex digits after \x, rust only up to 0x7f
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", u32::from(c))),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn generate_printpdf(fields: &[CodeField]) -> String {
+    let mut out = String::from(
+        "// Field positions for printpdf, in millimeters from the bottom left corner of the page.\n\
+         use printpdf::Mm;\n\
+         \n\
+         pub struct Field {\n    \
+             pub id: &'static str,\n    \
+             /// Page index starting at 0.\n    \
+             pub page: usize,\n    \
+             pub x: Mm,\n    \
+             pub y: Mm,\n    \
+             pub width: Mm,\n    \
+             pub height: Mm,\n\
+         }\n",
+    );
+    for (field, name) in fields.iter().zip(rust_constant_names(fields)) {
+        out.push_str(&format!(
+            "\npub const {name}: Field = Field {{\n    \
+                 id: {},\n    \
+                 page: {},\n    \
+                 x: Mm({:.2}),\n    \
+                 y: Mm({:.2}),\n    \
+                 width: Mm({:.2}),\n    \
+                 height: Mm({:.2}),\n\
+             }};\n",
+            quoted(&field.unique_id),
+            field.page,
+            units::pt_to_mm(field.x),
+            units::pt_to_mm(field.y),
+            units::pt_to_mm(field.width),
+            units::pt_to_mm(field.height),
+        ));
+    }
+    out
+}
+
+fn generate_lopdf(fields: &[CodeField]) -> String {
+    let mut out = String::from(
+        "// Field rects for lopdf, in points from the bottom left corner of the page.\n\
+         \n\
+         pub struct Field {\n    \
+             pub id: &'static str,\n    \
+             /// Page index starting at 0.\n    \
+             pub page: usize,\n    \
+             /// Left, bottom, right and top as in an annotation `Rect`.\n    \
+             pub rect: [f32; 4],\n\
+         }\n",
+    );
+    for (field, name) in fields.iter().zip(rust_constant_names(fields)) {
+        out.push_str(&format!(
+            "\npub const {name}: Field = Field {{\n    \
+                 id: {},\n    \
+                 page: {},\n    \
+                 rect: [{:.2}, {:.2}, {:.2}, {:.2}],\n\
+             }};\n",
+            quoted(&field.unique_id),
+            field.page,
+            field.x,
+            field.y,
+            field.x + field.width,
+            field.y + field.height,
+        ));
+    }
+    out
+}
+
+fn generate_reportlab(fields: &[CodeField]) -> String {
+    let mut out = String::from(
+        "# Field positions for reportlab, in points from the bottom left corner of the page.\n\
+         from typing import NamedTuple\n\
+         \n\
+         \n\
+         class Field(NamedTuple):\n    \
+             page: int\n    \
+             x: float\n    \
+             y: float\n    \
+             width: float\n    \
+             height: float\n\
+         \n\
+         \n\
+         FIELDS = [\n",
+    );
+    for field in fields {
+        out.push_str(&format!(
+            "    ({}, Field(page={}, x={:.2}, y={:.2}, width={:.2}, height={:.2})),\n",
+            quoted(&field.unique_id),
+            field.page,
+            field.x,
+            field.y,
+            field.width,
+            field.height,
+        ));
+    }
+    out.push_str("]\n");
+    out
+}
+
+fn generate_pdf_lib(fields: &[CodeField]) -> String {
+    let mut out = String::from(
+        "// Field positions for pdf-lib, in points from the bottom left corner of the page.\n\
+         export const fields = [\n",
+    );
+    for field in fields {
+        out.push_str(&format!(
+            "  {{ id: {}, page: {}, x: {:.2}, y: {:.2}, width: {:.2}, height: {:.2} }},\n",
+            quoted(&field.unique_id),
+            field.page,
+            field.x,
+            field.y,
+            field.width,
+            field.height,
+        ));
+    }
+    out.push_str("];\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_escapes_control_characters() {
+        assert_eq!(quoted("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(quoted("line\r\n\tend"), r#""line\r\n\tend""#);
+        assert_eq!(quoted("\u{0}\u{1f}\u{7f}ä"), r#""\x00\x1f\x7fä""#);
+    }
+}
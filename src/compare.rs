@@ -0,0 +1,464 @@
+// compare.rs
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+
+use egui::{Color32, Rect, Stroke, StrokeKind, TextureHandle, pos2, vec2};
+use image::GrayImage;
+use pdfium_render::prelude::Pdfium;
+
+use crate::app;
+use crate::layout::Layout;
+use crate::pdf_load;
+
+/// Side length in pixels of the cells pages are compared in.
+const CELL_SIZE: u32 = 8;
+
+/// Brightness difference of a pixel which counts as a change, small ones are antialiasing.
+const CHANGE_THRESHOLD: u8 = 48;
+
+/// Changed cells are grouped into regions, this many empty cells apart still join a region.
+const REGION_GAP_CELLS: usize = 1;
+
+/// Per pixel brightness difference of two pages, pixels outside of one page count as white.
+fn difference(a: &GrayImage, b: &GrayImage) -> GrayImage {
+    let width = a.width().max(b.width());
+    let height = a.height().max(b.height());
+    GrayImage::from_fn(width, height, |x, y| {
+        let brightness = |image: &GrayImage| {
+            image
+                .get_pixel_checked(x, y)
+                .map_or(255, |pixel| pixel.0[0])
+        };
+        image::Luma([brightness(a).abs_diff(brightness(b))])
+    })
+}
+
+/// Bounding boxes of the changed areas of a [`difference`], in pixels which are pdf points.
+fn changed_regions(difference: &GrayImage) -> Vec<Rect> {
+    let columns = difference.width().div_ceil(CELL_SIZE) as usize;
+    let rows = difference.height().div_ceil(CELL_SIZE) as usize;
+    let mut changed = vec![false; columns * rows];
+    for (x, y, pixel) in difference.enumerate_pixels() {
+        if pixel.0[0] > CHANGE_THRESHOLD {
+            let index = (y / CELL_SIZE) as usize * columns + (x / CELL_SIZE) as usize;
+            if let Some(cell) = changed.get_mut(index) {
+                *cell = true;
+            }
+        }
+    }
+    let mut regions = Vec::new();
+    for start in 0..changed.len() {
+        if changed.get(start) != Some(&true) {
+            continue;
+        }
+        // flood fill the cells of one region, clearing them on the way
+        let (mut min_column, mut min_row) = (start % columns, start / columns);
+        let (mut max_column, mut max_row) = (min_column, min_row);
+        let mut stack = vec![start];
+        if let Some(cell) = changed.get_mut(start) {
+            *cell = false;
+        }
+        while let Some(index) = stack.pop() {
+            let (column, row) = (index % columns, index / columns);
+            min_column = min_column.min(column);
+            max_column = max_column.max(column);
+            min_row = min_row.min(row);
+            max_row = max_row.max(row);
+            let neighbour_rows = row.saturating_sub(REGION_GAP_CELLS + 1)
+                ..=(row + REGION_GAP_CELLS + 1).min(rows - 1);
+            for neighbour_row in neighbour_rows {
+                let neighbour_columns = column.saturating_sub(REGION_GAP_CELLS + 1)
+                    ..=(column + REGION_GAP_CELLS + 1).min(columns - 1);
+                for neighbour_column in neighbour_columns {
+                    let neighbour = neighbour_row * columns + neighbour_column;
+                    if let Some(cell) = changed.get_mut(neighbour)
+                        && *cell
+                    {
+                        *cell = false;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+        }
+        let cell = CELL_SIZE as f32;
+        regions.push(Rect::from_min_max(
+            pos2(min_column as f32 * cell, min_row as f32 * cell),
+            pos2((max_column + 1) as f32 * cell, (max_row + 1) as f32 * cell),
+        ));
+    }
+    regions
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareMode {
+    SideBySide,
+    /// The other document semi transparent over the current one.
+    Blend,
+    Difference,
+}
+
+struct ComparedPage {
+    current: Option<TextureHandle>,
+    other: Option<TextureHandle>,
+    difference: TextureHandle,
+    /// Size of the larger of both pages in points.
+    size: egui::Vec2,
+    changed_regions: Vec<Rect>,
+}
+
+fn render_pages(path: &Path) -> Result<Vec<GrayImage>, String> {
+    let pdfium = Pdfium::default();
+    pdf_load::load_pdf_native(&pdfium, path)
+        .and_then(app::create_images_from_pdf)
+        .map(|images| images.iter().map(image::DynamicImage::to_luma8).collect())
+        .map_err(|e| format!("Could not render file='{}': {e}", path.to_string_lossy()))
+}
+
+fn gray_texture(ctx: &egui::Context, name: &str, image: &GrayImage) -> TextureHandle {
+    let size = [image.width() as usize, image.height() as usize];
+    let color_image = egui::ColorImage::from_gray(size, image.as_raw());
+    ctx.load_texture(name, color_image, Default::default())
+}
+
+/// Page of both documents and their difference, before it is uploaded as textures.
+struct PageComparison {
+    current: Option<GrayImage>,
+    other: Option<GrayImage>,
+    /// Unchanged pixels white, changes dark.
+    difference: GrayImage,
+    changed_regions: Vec<Rect>,
+}
+
+fn compare_page(current: Option<GrayImage>, other: Option<GrayImage>) -> PageComparison {
+    let blank = GrayImage::new(0, 0);
+    let mut difference = difference(
+        current.as_ref().unwrap_or(&blank),
+        other.as_ref().unwrap_or(&blank),
+    );
+    let changed_regions = changed_regions(&difference);
+    image::imageops::invert(&mut difference);
+    PageComparison {
+        current,
+        other,
+        difference,
+        changed_regions,
+    }
+}
+
+/// Renders both documents and compares them page by page.
+fn compare_documents(
+    current_path: &Path,
+    other_path: &Path,
+) -> Result<Vec<PageComparison>, String> {
+    let current = render_pages(current_path)?;
+    let other = render_pages(other_path)?;
+    let page_count = current.len().max(other.len());
+    let mut current = current.into_iter();
+    let mut other = other.into_iter();
+    Ok((0..page_count)
+        .map(|_| compare_page(current.next(), other.next()))
+        .collect())
+}
+
+impl PageComparison {
+    fn into_textures(self, ctx: &egui::Context, index: usize) -> ComparedPage {
+        let texture = |name: &str, image: Option<&GrayImage>| {
+            image.map(|image| gray_texture(ctx, &format!("compare_{name}_{index}"), image))
+        };
+        ComparedPage {
+            current: texture("current", self.current.as_ref()),
+            other: texture("other", self.other.as_ref()),
+            difference: gray_texture(
+                ctx,
+                &format!("compare_difference_{index}"),
+                &self.difference,
+            ),
+            size: vec2(
+                self.difference.width() as f32,
+                self.difference.height() as f32,
+            ),
+            changed_regions: self.changed_regions,
+        }
+    }
+}
+
+/// Two documents page by page with their changed regions.
+pub struct CompareWindow {
+    pub open: bool,
+    other_path: Option<PathBuf>,
+    /// Comparison running on another thread.
+    receiver: Option<mpsc::Receiver<Result<Vec<PageComparison>, String>>>,
+    pages: Result<Vec<ComparedPage>, String>,
+    page: usize,
+    mode: CompareMode,
+    /// Opacity of the other document in [`CompareMode::Blend`].
+    opacity: f32,
+    zoom: f32,
+}
+
+impl Default for CompareWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            other_path: None,
+            receiver: None,
+            pages: Ok(Vec::new()),
+            page: 0,
+            mode: CompareMode::SideBySide,
+            opacity: 0.5,
+            zoom: 0.75,
+        }
+    }
+}
+
+impl CompareWindow {
+    /// Opens the window and compares both documents on another thread.
+    pub fn start(&mut self, current_path: PathBuf, other_path: PathBuf) {
+        let (producer, receiver) = mpsc::channel();
+        let thread_other_path = other_path.clone();
+        std::thread::spawn(move || {
+            producer
+                .send(compare_documents(&current_path, &thread_other_path))
+                .ok();
+        });
+        self.receiver = Some(receiver);
+        self.pages = Ok(Vec::new());
+        self.other_path = Some(other_path);
+        self.page = 0;
+        self.open = true;
+    }
+
+    /// Takes the result of the comparison thread, returns whether it is still running.
+    fn poll(&mut self, ctx: &egui::Context) -> bool {
+        let Some(receiver) = &self.receiver else {
+            return false;
+        };
+        self.pages = match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => {
+                Err("Error: Connection to the comparison was lost.".to_owned())
+            }
+            Ok(result) => result.map(|pages| {
+                pages
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, page)| page.into_textures(ctx, index))
+                    .collect()
+            }),
+        };
+        self.receiver = None;
+        false
+    }
+
+    /// Page selection, compare mode and zoom for documents with `page_count` pages.
+    fn ui_controls(&mut self, ui: &mut egui::Ui, page_count: usize) {
+        ui.label("page");
+        let last_page = page_count.saturating_sub(1);
+        ui.add(
+            egui::DragValue::new(&mut self.page)
+                .range(0..=last_page)
+                .custom_formatter(|page, _| format!("{} of {}", page as usize + 1, last_page + 1))
+                .custom_parser(|text| {
+                    let page: f64 = text.split_whitespace().next()?.parse().ok()?;
+                    Some(page - 1.0)
+                }),
+        );
+        ui.separator();
+        ui.radio_value(&mut self.mode, CompareMode::SideBySide, "side by side");
+        ui.radio_value(&mut self.mode, CompareMode::Blend, "blend");
+        ui.radio_value(&mut self.mode, CompareMode::Difference, "difference");
+        if self.mode == CompareMode::Blend {
+            ui.add(egui::Slider::new(&mut self.opacity, 0.0..=1.0).text("opacity"));
+        }
+        ui.add(egui::Slider::new(&mut self.zoom, 0.25..=2.0).text("zoom"));
+    }
+
+    /// Shows the window, `layout` belongs to the current document.
+    pub fn show(&mut self, ctx: &egui::Context, layout: &Layout) {
+        let mut open = self.open;
+        egui::Window::new("Compare revisions")
+            .open(&mut open)
+            .default_size([900.0, 700.0])
+            .show(ctx, |ui| {
+                if let Some(path) = &self.other_path {
+                    ui.label(format!("compared with: {}", path.to_string_lossy()));
+                }
+                if self.poll(ctx) {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Rendering and comparing the documents…");
+                    });
+                    return;
+                }
+                let page_count = match &self.pages {
+                    Ok(pages) => pages.len(),
+                    Err(e) => {
+                        ui.label(e.as_str());
+                        return;
+                    }
+                };
+                ui.horizontal(|ui| self.ui_controls(ui, page_count));
+                let Some(page) = self
+                    .pages
+                    .as_ref()
+                    .ok()
+                    .and_then(|pages| pages.get(self.page))
+                else {
+                    ui.label("The documents have no pages.");
+                    return;
+                };
+                let fields = layout
+                    .pages
+                    .get(self.page)
+                    .map_or(&[][..], |page| &page.fields);
+                let changed_fields: Vec<(&str, Rect)> = fields
+                    .iter()
+                    .map(|field| {
+                        let rect = Rect::from_min_size(
+                            pos2(field.pos_x, field.pos_y),
+                            vec2(field.width, field.height),
+                        );
+                        (field.unique_id.as_str(), rect)
+                    })
+                    .filter(|(_, rect)| {
+                        page.changed_regions
+                            .iter()
+                            .any(|region| region.intersects(*rect))
+                    })
+                    .collect();
+                let names: Vec<&str> = changed_fields.iter().map(|(name, _)| *name).collect();
+                ui.label(format!(
+                    "{} changed regions, fields in changed regions: {}",
+                    page.changed_regions.len(),
+                    if names.is_empty() {
+                        "none".to_owned()
+                    } else {
+                        names.join(", ")
+                    }
+                ));
+                let field_rects: Vec<Rect> = changed_fields.iter().map(|(_, rect)| *rect).collect();
+                let zoom = self.zoom;
+                egui::ScrollArea::both().show(ui, |ui| {
+                    ui.horizontal_top(|ui| {
+                        let opaque = Color32::WHITE;
+                        match self.mode {
+                            CompareMode::SideBySide => {
+                                let current = [(page.current.as_ref(), opaque)];
+                                draw_page(ui, page, &current, &field_rects, zoom);
+                                let other = [(page.other.as_ref(), opaque)];
+                                draw_page(ui, page, &other, &field_rects, zoom);
+                            }
+                            CompareMode::Blend => {
+                                let tint = Color32::from_white_alpha((self.opacity * 255.0) as u8);
+                                let textures =
+                                    [(page.current.as_ref(), opaque), (page.other.as_ref(), tint)];
+                                draw_page(ui, page, &textures, &field_rects, zoom);
+                            }
+                            CompareMode::Difference => {
+                                let difference = [(Some(&page.difference), opaque)];
+                                draw_page(ui, page, &difference, &field_rects, zoom);
+                            }
+                        }
+                    });
+                });
+            });
+        self.open = open;
+    }
+}
+
+fn full_uv() -> Rect {
+    Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0))
+}
+
+/// Draws the tinted textures on a white page, then the changed regions and the fields in them.
+fn draw_page(
+    ui: &mut egui::Ui,
+    page: &ComparedPage,
+    textures: &[(Option<&TextureHandle>, Color32)],
+    field_rects: &[Rect],
+    zoom: f32,
+) {
+    let (response, painter) = ui.allocate_painter(page.size * zoom, egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_filled(rect, 0.0, Color32::WHITE);
+    for (texture, tint) in textures {
+        if let Some(texture) = texture {
+            // pages smaller than the larger of both keep their scale
+            let texture_rect = Rect::from_min_size(rect.min, texture.size_vec2() * zoom);
+            painter.image(texture.id(), texture_rect, full_uv(), *tint);
+        }
+    }
+    let to_screen = |page_rect: &Rect| {
+        Rect::from_min_max(
+            rect.min + page_rect.min.to_vec2() * zoom,
+            rect.min + page_rect.max.to_vec2() * zoom,
+        )
+    };
+    for region in &page.changed_regions {
+        let stroke = Stroke::new(2.0, Color32::RED);
+        painter.rect_stroke(to_screen(region), 0.0, stroke, StrokeKind::Outside);
+    }
+    for field_rect in field_rects {
+        let stroke = Stroke::new(2.0, Color32::BLUE);
+        painter.rect_stroke(to_screen(field_rect), 0.0, stroke, StrokeKind::Inside);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White page of 64 by 48 points with black squares of `size` at `corners`.
+    fn page(corners: &[(u32, u32)], size: u32) -> GrayImage {
+        let mut image = GrayImage::from_pixel(64, 48, image::Luma([255]));
+        for &(left, top) in corners {
+            for y in top..top + size {
+                for x in left..left + size {
+                    image.put_pixel(x, y, image::Luma([0]));
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn identical_pages_have_no_changes() {
+        let a = page(&[(4, 4)], 10);
+        assert!(changed_regions(&difference(&a, &a)).is_empty());
+    }
+
+    #[test]
+    fn faint_differences_are_ignored() {
+        let a = page(&[], 0);
+        let b = GrayImage::from_pixel(64, 48, image::Luma([255 - CHANGE_THRESHOLD]));
+        assert!(changed_regions(&difference(&a, &b)).is_empty());
+    }
+
+    #[test]
+    fn changes_are_grouped_into_cell_aligned_regions() {
+        let a = page(&[], 0);
+        // two squares close to each other and one far away
+        let b = page(&[(2, 2), (12, 2), (50, 36)], 3);
+        let regions = changed_regions(&difference(&a, &b));
+        assert_eq!(
+            regions,
+            [
+                Rect::from_min_max(pos2(0., 0.), pos2(16., 8.)),
+                Rect::from_min_max(pos2(48., 32.), pos2(56., 40.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn pages_of_different_size_differ_outside_the_smaller_one() {
+        let small = GrayImage::from_pixel(16, 16, image::Luma([0]));
+        let large = GrayImage::from_pixel(32, 16, image::Luma([0]));
+        let difference = difference(&small, &large);
+        assert_eq!(difference.dimensions(), (32, 16));
+        assert_eq!(
+            changed_regions(&difference),
+            [Rect::from_min_max(pos2(16., 0.), pos2(32., 16.))]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+// csv_table.rs
+
+use std::fmt;
+
+/// Parsed csv file with a header row.
+#[derive(Debug, Clone, Default)]
+pub struct CsvTable {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum CsvError {
+    Empty,
+    UnterminatedQuote { line: usize },
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "csv file has no header row"),
+            Self::UnterminatedQuote { line } => {
+                write!(f, "quoted value starting in line {line} is never closed")
+            }
+        }
+    }
+}
+
+impl CsvTable {
+    /// Parses `text` as csv. The delimiter is `;` if the header row contains more
+    /// semicolons than commas, as spreadsheet programs with german locale export them.
+    /// A header row without either has a single column, its values may contain both.
+    pub fn parse(text: &str) -> Result<Self, CsvError> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let header_line = text.lines().next().unwrap_or_default();
+        let semicolons = header_line.matches(';').count();
+        let commas = header_line.matches(',').count();
+        let delimiter = if semicolons > commas {
+            Some(';')
+        } else if commas > 0 {
+            Some(',')
+        } else {
+            None
+        };
+
+        let mut records = parse_records(text, delimiter)?.into_iter();
+        let headers = records
+            .next()
+            .ok_or(CsvError::Empty)?
+            .into_iter()
+            .map(|header| header.trim().to_owned())
+            .collect();
+        let rows = records
+            .filter(|record| record.iter().any(|value| !value.is_empty()))
+            .collect();
+        Ok(Self { headers, rows })
+    }
+
+    /// Writes the table as comma separated csv, quoting values where needed.
+    pub fn to_csv_string(&self) -> String {
+        let mut out = String::new();
+        for record in std::iter::once(&self.headers).chain(&self.rows) {
+            for (index, value) in record.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                if value.contains([',', ';', '"', '\n', '\r']) {
+                    out.push('"');
+                    out.push_str(&value.replace('"', "\"\""));
+                    out.push('"');
+                } else {
+                    out.push_str(value);
+                }
+            }
+            out.push_str("\r\n");
+        }
+        out
+    }
+
+    pub fn column_index(&self, header: &str) -> Option<usize> {
+        self.headers.iter().position(|column| column == header)
+    }
+
+    /// Value of `row` in the column named `header`, missing trailing cells count as empty.
+    pub fn value<'a>(&self, row: &'a [String], header: &str) -> Option<&'a str> {
+        let index = self.column_index(header)?;
+        Some(row.get(index).map(String::as_str).unwrap_or_default())
+    }
+}
+
+fn parse_records(text: &str, delimiter: Option<char>) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut value = String::new();
+    let mut in_quotes = false;
+    let mut quote_line = 0;
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    value.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    value.push(c);
+                }
+                _ => value.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if value.is_empty() => {
+                in_quotes = true;
+                quote_line = line;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                line += 1;
+                record.push(std::mem::take(&mut value));
+                records.push(std::mem::take(&mut record));
+            }
+            c if Some(c) == delimiter => record.push(std::mem::take(&mut value)),
+            _ => value.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(CsvError::UnterminatedQuote { line: quote_line });
+    }
+    if !value.is_empty() || !record.is_empty() {
+        record.push(value);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_values_keep_delimiters_and_quotes() {
+        let table = CsvTable::parse("name,address\n\"Doe, Jane\",\"Main St \"\"5\"\"\"\n").unwrap();
+        assert_eq!(table.headers, ["name", "address"]);
+        assert_eq!(table.rows, [["Doe, Jane", "Main St \"5\""]]);
+    }
+
+    #[test]
+    fn quoted_values_span_lines() {
+        let table = CsvTable::parse("note\n\"first\nsecond\"\n").unwrap();
+        assert_eq!(table.rows, [["first\nsecond"]]);
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let table = CsvTable::parse("a,b\r\n1,2\r\n\r\n3,4").unwrap();
+        assert_eq!(table.headers, ["a", "b"]);
+        assert_eq!(table.rows, [["1", "2"], ["3", "4"]]);
+    }
+
+    #[test]
+    fn semicolon_delimiter_from_header() {
+        let table = CsvTable::parse("\u{feff}name;amount\nDoe;1,50\n").unwrap();
+        assert_eq!(table.headers, ["name", "amount"]);
+        assert_eq!(table.rows, [["Doe", "1,50"]]);
+    }
+
+    #[test]
+    fn one_column_file_is_not_split() {
+        let table = CsvTable::parse("name\nDoe, Jane\nRoe; John\n").unwrap();
+        assert_eq!(table.headers, ["name"]);
+        assert_eq!(table.rows, [["Doe, Jane"], ["Roe; John"]]);
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let result = CsvTable::parse("a\n1\n\"open\n");
+        assert!(matches!(
+            result,
+            Err(CsvError::UnterminatedQuote { line: 3 })
+        ));
+    }
+
+    #[test]
+    fn written_csv_parses_back() {
+        let table = CsvTable {
+            headers: vec!["name".to_owned(), "note".to_owned()],
+            rows: vec![vec!["Doe, Jane".to_owned(), "say \"hi\"\nbye".to_owned()]],
+        };
+        let parsed = CsvTable::parse(&table.to_csv_string()).unwrap();
+        assert_eq!(parsed.headers, table.headers);
+        assert_eq!(parsed.rows, table.rows);
+    }
+}
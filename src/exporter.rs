@@ -0,0 +1,304 @@
+// exporter.rs
+
+use std::path::Path;
+
+use pdfium_render::prelude::Pdfium;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::acroform;
+use crate::calibration::CalibrationProfile;
+use crate::code_export::CodeTarget;
+use crate::form_data;
+use crate::json;
+use crate::layout::Layout;
+use crate::pdf_export;
+
+/// State of the app an export may depend on besides the layout.
+pub struct ExportContext<'a> {
+    pub pdf_file_path: Option<&'a Path>,
+    pub calibration: Option<&'a CalibrationProfile>,
+}
+
+impl ExportContext<'_> {
+    fn pdf_file_path(&self) -> Result<&Path, String> {
+        self.pdf_file_path
+            .ok_or_else(|| "No pdf file is loaded.".to_owned())
+    }
+
+    fn pdf_file_name(&self) -> Option<String> {
+        Some(
+            self.pdf_file_path?
+                .file_name()?
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+/// Output format listed in the File → Export menu.
+pub trait Exporter {
+    /// Menu entry of the format.
+    fn name(&self) -> &'static str;
+
+    /// Appended to the name of the pdf file to suggest a file name, e.g. `overlay`.
+    fn file_suffix(&self) -> &'static str;
+
+    fn file_extension(&self) -> &'static str;
+
+    /// Exporters with options get a submenu showing [`Self::options_ui`] above the export button.
+    fn has_options(&self) -> bool {
+        false
+    }
+
+    fn options_ui(&mut self, _ui: &mut egui::Ui) {}
+
+    /// Contents of the exported file.
+    ///
+    /// # Errors
+    /// A message for the user if the export is not possible.
+    fn export(&self, layout: &Layout, context: &ExportContext<'_>) -> Result<Vec<u8>, String>;
+}
+
+/// All exporters in menu order.
+pub struct ExporterRegistry {
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl Default for ExporterRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            exporters: Vec::new(),
+        };
+        registry.register(Box::new(OverlayPdfExporter));
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            registry.register(Box::new(FilledPdfExporter));
+            registry.register(Box::new(FillablePdfExporter));
+        }
+        registry.register(Box::new(JsonValuesExporter));
+        registry.register(Box::new(FdfExporter));
+        registry.register(Box::new(XfdfExporter));
+        registry.register(Box::new(CodeExporter {
+            target: CodeTarget::PrintPdf,
+        }));
+        registry
+    }
+}
+
+impl ExporterRegistry {
+    pub fn register(&mut self, exporter: Box<dyn Exporter>) {
+        self.exporters.push(exporter);
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut (dyn Exporter + 'static)> {
+        self.exporters.iter_mut().map(|exporter| &mut **exporter)
+    }
+}
+
+/// Shows one entry per exporter, returns the file name and contents of a clicked export.
+pub fn export_menu(
+    ui: &mut egui::Ui,
+    registry: &mut ExporterRegistry,
+    layout: &Layout,
+    context: &ExportContext<'_>,
+) -> Option<Result<(String, Vec<u8>), String>> {
+    let mut result = None;
+    for exporter in registry.iter_mut() {
+        let clicked = if exporter.has_options() {
+            ui.menu_button(exporter.name(), |ui| {
+                exporter.options_ui(ui);
+                ui.button("Export…").clicked()
+            })
+            .inner
+            .unwrap_or(false)
+        } else {
+            ui.button(format!("{}…", exporter.name())).clicked()
+        };
+        if clicked {
+            let stem = context
+                .pdf_file_path
+                .and_then(Path::file_stem)
+                .map_or_else(|| "layout".into(), |stem| stem.to_string_lossy());
+            let file_name = format!(
+                "{stem}_{}.{}",
+                exporter.file_suffix(),
+                exporter.file_extension()
+            );
+            result = Some(
+                exporter
+                    .export(layout, context)
+                    .map(|bytes| (file_name, bytes)),
+            );
+        }
+    }
+    result
+}
+
+struct OverlayPdfExporter;
+
+impl Exporter for OverlayPdfExporter {
+    fn name(&self) -> &'static str {
+        "Overlay PDF"
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        "overlay"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn export(&self, layout: &Layout, context: &ExportContext<'_>) -> Result<Vec<u8>, String> {
+        pdf_export::create_overlay_pdf(&Pdfium::default(), layout, context.calibration)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct FilledPdfExporter;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Exporter for FilledPdfExporter {
+    fn name(&self) -> &'static str {
+        "Filled PDF"
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        "filled"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn export(&self, layout: &Layout, context: &ExportContext<'_>) -> Result<Vec<u8>, String> {
+        pdf_export::create_filled_pdf(
+            &Pdfium::default(),
+            context.pdf_file_path()?,
+            layout,
+            context.calibration,
+        )
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Source document with the fields as fillable form fields.
+#[cfg(not(target_arch = "wasm32"))]
+struct FillablePdfExporter;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Exporter for FillablePdfExporter {
+    fn name(&self) -> &'static str {
+        "Fillable PDF"
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        "fillable"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn export(&self, layout: &Layout, context: &ExportContext<'_>) -> Result<Vec<u8>, String> {
+        acroform::create_fillable_pdf(&Pdfium::default(), context.pdf_file_path()?, layout)
+            .map_err(|e| e.to_string())
+    }
+}
+
+struct JsonValuesExporter;
+
+impl Exporter for JsonValuesExporter {
+    fn name(&self) -> &'static str {
+        "Values as json"
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        "values"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn export(&self, layout: &Layout, _context: &ExportContext<'_>) -> Result<Vec<u8>, String> {
+        let values = json::record_to_json(&layout.field_values());
+        serde_json::to_vec_pretty(&values).map_err(|e| e.to_string())
+    }
+}
+
+struct FdfExporter;
+
+impl Exporter for FdfExporter {
+    fn name(&self) -> &'static str {
+        "Values as fdf"
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        "values"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "fdf"
+    }
+
+    fn export(&self, layout: &Layout, context: &ExportContext<'_>) -> Result<Vec<u8>, String> {
+        Ok(form_data::to_fdf(
+            layout,
+            context.pdf_file_name().as_deref(),
+        ))
+    }
+}
+
+struct XfdfExporter;
+
+impl Exporter for XfdfExporter {
+    fn name(&self) -> &'static str {
+        "Values as xfdf"
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        "values"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        "xfdf"
+    }
+
+    fn export(&self, layout: &Layout, context: &ExportContext<'_>) -> Result<Vec<u8>, String> {
+        Ok(form_data::to_xfdf(layout, context.pdf_file_name().as_deref()).into_bytes())
+    }
+}
+
+struct CodeExporter {
+    target: CodeTarget,
+}
+
+impl Exporter for CodeExporter {
+    fn name(&self) -> &'static str {
+        "Code"
+    }
+
+    fn file_suffix(&self) -> &'static str {
+        "fields"
+    }
+
+    fn file_extension(&self) -> &'static str {
+        self.target.file_extension()
+    }
+
+    fn has_options(&self) -> bool {
+        true
+    }
+
+    fn options_ui(&mut self, ui: &mut egui::Ui) {
+        for target in CodeTarget::ALL {
+            ui.radio_value(&mut self.target, target, target.name());
+        }
+    }
+
+    fn export(&self, layout: &Layout, _context: &ExportContext<'_>) -> Result<Vec<u8>, String> {
+        Ok(self.target.generate(layout).into_bytes())
+    }
+}
@@ -0,0 +1,104 @@
+// extraction.rs
+
+use std::fmt;
+
+use pdfium_render::prelude::{PdfDocument, PdfPoints, PdfRect, PdfiumError};
+
+use crate::layout::{FieldValues, Layout};
+use crate::pdf_text_input::PdfInputFieldSerde;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractionWarning {
+    /// The layout has fields on a page the document does not have, `page` starts at 1.
+    MissingPage {
+        page: usize,
+    },
+    EmptyRequiredField {
+        unique_id: String,
+    },
+    /// Text overlapping the field continues outside of its rect.
+    TextOutsideField {
+        unique_id: String,
+    },
+}
+
+impl fmt::Display for ExtractionWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPage { page } => write!(f, "page {page} is missing"),
+            Self::EmptyRequiredField { unique_id } => {
+                write!(f, "required field '{unique_id}' is empty")
+            }
+            Self::TextOutsideField { unique_id } => {
+                write!(f, "text of field '{unique_id}' spills outside the field")
+            }
+        }
+    }
+}
+
+pub struct Extraction {
+    pub values: FieldValues,
+    pub warnings: Vec<ExtractionWarning>,
+}
+
+/// Reads the text layer of `document` inside every field rect of `layout`.
+///
+/// Fields without a `unique_id` are skipped, fields on pages missing in `document` get empty values.
+pub fn extract_fields(
+    document: &PdfDocument<'_>,
+    layout: &Layout,
+) -> Result<Extraction, PdfiumError> {
+    let mut values = FieldValues::new();
+    let mut warnings = Vec::new();
+    for (page_index, layout_page) in layout.pages.iter().enumerate() {
+        let fields = layout_page
+            .fields
+            .iter()
+            .filter(|field| !field.unique_id.is_empty());
+        let Ok(page) = document.pages().get(page_index as u16) else {
+            if !layout_page.fields.is_empty() {
+                warnings.push(ExtractionWarning::MissingPage {
+                    page: page_index + 1,
+                });
+            }
+            values.extend(fields.map(|field| (field.unique_id.clone(), String::new())));
+            continue;
+        };
+        let page_height = page.height().value;
+        let text = page.text()?;
+        for field in fields {
+            let rect = field_pdf_rect(field, page_height);
+            let value = normalize_whitespace(&text.inside_rect(rect));
+            if field.required && value.is_empty() {
+                warnings.push(ExtractionWarning::EmptyRequiredField {
+                    unique_id: field.unique_id.clone(),
+                });
+            }
+            if text
+                .segments()
+                .iter()
+                .any(|segment| segment.does_overlap_rect(&rect) && !segment.is_inside_rect(&rect))
+            {
+                warnings.push(ExtractionWarning::TextOutsideField {
+                    unique_id: field.unique_id.clone(),
+                });
+            }
+            values.insert(field.unique_id.clone(), value);
+        }
+    }
+    Ok(Extraction { values, warnings })
+}
+
+/// Field rect in pdf coordinates with the origin in the bottom left corner of the page.
+pub fn field_pdf_rect(field: &PdfInputFieldSerde, page_height: f32) -> PdfRect {
+    PdfRect::new(
+        PdfPoints::new(page_height - field.pos_y - field.height),
+        PdfPoints::new(field.pos_x),
+        PdfPoints::new(page_height - field.pos_y),
+        PdfPoints::new(field.pos_x + field.width),
+    )
+}
+
+fn normalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
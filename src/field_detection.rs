@@ -0,0 +1,555 @@
+// field_detection.rs
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+
+use egui::{Color32, Rect, Stroke, StrokeKind, pos2};
+use image::GrayImage;
+use pdfium_render::prelude::{Pdfium, PdfiumError};
+
+use crate::app;
+use crate::layout::Layout;
+use crate::pdf_load;
+use crate::pdf_text_input::PdfInputFieldKind;
+use crate::snapping::{SnapGuides, SnapLine};
+
+/// Smallest box in points proposed as a field, smaller ones are tick marks or letters.
+const MIN_FIELD_SIZE: f32 = 8.0;
+
+/// Taller boxes are frames around sections rather than fields.
+const MAX_FIELD_HEIGHT: f32 = 80.0;
+
+/// Line ends and corners closer than this in points meet.
+const LINE_TOLERANCE: f32 = 2.0;
+
+/// Pixels darker than this belong to a line on a scanned page.
+const DARK_PIXEL: u8 = 128;
+
+/// Dark runs of pixels shorter than this are text rather than lines.
+const MIN_BITMAP_LINE: u32 = 20;
+
+/// Existing fields covering this much of a detected box already fill it.
+const MAX_FIELD_OVERLAP: f32 = 0.5;
+
+/// Cells at most this much wider than high can hold one character of a comb field, wider ones
+/// are the columns of a table.
+const MAX_COMB_CELL_ASPECT: f32 = 1.5;
+
+/// Box proposed as a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetectedBox {
+    /// In points from the top left corner of the page.
+    pub rect: Rect,
+    /// [`PdfInputFieldKind::Comb`] for a row of equally wide cells, text otherwise.
+    pub kind: PdfInputFieldKind,
+}
+
+/// Runs of at least [`MIN_BITMAP_LINE`] dark pixels in a row or column of `length` pixels.
+fn dark_runs(length: u32, is_dark: impl Fn(u32) -> bool) -> Vec<(u32, u32)> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for position in 0..=length {
+        match (position < length && is_dark(position), start) {
+            (true, None) => start = Some(position),
+            (false, Some(first)) => {
+                if position - first >= MIN_BITMAP_LINE {
+                    runs.push((first, position));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    runs
+}
+
+/// Horizontal and vertical lines of a rendered page, one per row or column of pixels they cover.
+fn bitmap_lines(image: &GrayImage) -> SnapGuides {
+    let mut guides = SnapGuides::default();
+    let is_dark = |x, y| image.get_pixel(x, y).0[0] < DARK_PIXEL;
+    // lines run through the middle of their pixels
+    for y in 0..image.height() {
+        let center = y as f32 + 0.5;
+        for (start, end) in dark_runs(image.width(), |x| is_dark(x, y)) {
+            guides.add_line(pos2(start as f32, center), pos2(end as f32, center));
+        }
+    }
+    for x in 0..image.width() {
+        let center = x as f32 + 0.5;
+        for (start, end) in dark_runs(image.height(), |y| is_dark(x, y)) {
+            guides.add_line(pos2(center, start as f32), pos2(center, end as f32));
+        }
+    }
+    guides
+}
+
+/// Joins overlapping lines less than [`LINE_TOLERANCE`] apart, e.g. the pixel rows of a thick
+/// line or a border drawn twice.
+fn merge_lines(mut lines: Vec<SnapLine>, horizontal: bool) -> Vec<SnapLine> {
+    // coordinate across the lines and the range along them
+    let across = |line: &SnapLine| if horizontal { line.from.y } else { line.from.x };
+    let along = |line: &SnapLine| {
+        if horizontal {
+            (line.from.x, line.to.x)
+        } else {
+            (line.from.y, line.to.y)
+        }
+    };
+    let line = |across: f32, start: f32, end: f32| {
+        if horizontal {
+            SnapLine {
+                from: pos2(start, across),
+                to: pos2(end, across),
+            }
+        } else {
+            SnapLine {
+                from: pos2(across, start),
+                to: pos2(across, end),
+            }
+        }
+    };
+    lines.sort_by(|a, b| across(a).total_cmp(&across(b)));
+    let mut merged: Vec<SnapLine> = Vec::new();
+    for next in lines {
+        let (start, end) = along(&next);
+        let existing = merged.iter_mut().rev().find(|existing| {
+            let (existing_start, existing_end) = along(existing);
+            (across(&next) - across(existing)).abs() < LINE_TOLERANCE
+                && start <= existing_end + LINE_TOLERANCE
+                && end >= existing_start - LINE_TOLERANCE
+        });
+        match existing {
+            Some(existing) => {
+                let (existing_start, existing_end) = along(existing);
+                *existing = line(
+                    f32::midpoint(across(existing), across(&next)),
+                    existing_start.min(start),
+                    existing_end.max(end),
+                );
+            }
+            None => merged.push(next),
+        }
+    }
+    merged
+}
+
+/// Cells enclosed by two horizontal and two vertical lines.
+///
+/// Boxes split by another line are reported as their cells, adjacent cells of a comb field as
+/// one box.
+fn boxes_from_lines(guides: &SnapGuides) -> Vec<DetectedBox> {
+    let merged = SnapGuides {
+        horizontal: merge_lines(guides.horizontal.clone(), true),
+        vertical: merge_lines(guides.vertical.clone(), false),
+        baselines: Vec::new(),
+    };
+    let mut boxes = Vec::new();
+    for top in &merged.horizontal {
+        for bottom in &merged.horizontal {
+            let height = bottom.from.y - top.from.y;
+            if !(MIN_FIELD_SIZE..=MAX_FIELD_HEIGHT).contains(&height) {
+                continue;
+            }
+            let left = top.from.x.max(bottom.from.x);
+            let right = top.to.x.min(bottom.to.x);
+            let mut sides: Vec<f32> = merged
+                .vertical
+                .iter()
+                .filter(|side| {
+                    side.from.x >= left - LINE_TOLERANCE
+                        && side.from.x <= right + LINE_TOLERANCE
+                        && side.from.y <= top.from.y + LINE_TOLERANCE
+                        && side.to.y >= bottom.from.y - LINE_TOLERANCE
+                })
+                .map(|side| side.from.x)
+                .collect();
+            sides.sort_by(f32::total_cmp);
+            sides.dedup_by(|a, b| (*a - *b).abs() < LINE_TOLERANCE);
+            let cells: Vec<Rect> = sides
+                .windows(2)
+                .filter_map(|pair| {
+                    let &[x_left, x_right] = pair else {
+                        return None;
+                    };
+                    Some(Rect::from_min_max(
+                        pos2(x_left, top.from.y),
+                        pos2(x_right, bottom.from.y),
+                    ))
+                })
+                .filter(|cell| cell.width() >= MIN_FIELD_SIZE && !is_split(&merged, *cell))
+                .collect();
+            boxes.extend(combine_cells(&cells));
+        }
+    }
+    boxes
+}
+
+/// Joins runs of adjacent, equally wide and narrow cells into one comb field each.
+///
+/// `cells` are sorted from left to right and share their top and bottom.
+fn combine_cells(cells: &[Rect]) -> Vec<DetectedBox> {
+    let mut boxes = Vec::new();
+    let mut rest = cells;
+    while let Some(&first) = rest.first() {
+        let adjacent = rest
+            .windows(2)
+            .take_while(|pair| {
+                let &[cell, next] = pair else {
+                    return false;
+                };
+                (next.left() - cell.right()).abs() < LINE_TOLERANCE
+                    && (next.width() - first.width()).abs() < LINE_TOLERANCE
+            })
+            .count();
+        let (run, remaining) = rest.split_at(adjacent + 1);
+        rest = remaining;
+        if run.len() >= 2 && first.width() <= first.height() * MAX_COMB_CELL_ASPECT {
+            boxes.push(DetectedBox {
+                rect: run.iter().fold(first, |comb, cell| comb.union(*cell)),
+                kind: PdfInputFieldKind::Comb {
+                    cells: u32::try_from(run.len()).unwrap_or(u32::MAX),
+                },
+            });
+        } else {
+            boxes.extend(run.iter().map(|&rect| DetectedBox {
+                rect,
+                kind: PdfInputFieldKind::Text,
+            }));
+        }
+    }
+    boxes
+}
+
+/// Whether a horizontal line crosses `cell`, which makes it two boxes rather than one.
+fn is_split(guides: &SnapGuides, cell: Rect) -> bool {
+    guides.horizontal.iter().any(|line| {
+        line.from.y > cell.top() + LINE_TOLERANCE
+            && line.from.y < cell.bottom() - LINE_TOLERANCE
+            && line.from.x <= cell.left() + LINE_TOLERANCE
+            && line.to.x >= cell.right() - LINE_TOLERANCE
+    })
+}
+
+/// Boxes of every page, from the vector lines in `guides` or, for pages without vector boxes
+/// like scans, from lines in the rendered page. Only these pages are rendered.
+pub fn detect_boxes(
+    pdf_path: &Path,
+    guides: &[SnapGuides],
+) -> Result<Vec<Vec<DetectedBox>>, String> {
+    let mut boxes: Vec<Vec<DetectedBox>> = guides.iter().map(boxes_from_lines).collect();
+    if boxes.iter().all(|page_boxes| !page_boxes.is_empty()) {
+        return Ok(boxes);
+    }
+    let render_error = |e: PdfiumError| {
+        format!(
+            "Could not render file='{}': {e}",
+            pdf_path.to_string_lossy()
+        )
+    };
+    let pdfium = Pdfium::default();
+    let document = pdf_load::load_pdf_native(&pdfium, pdf_path).map_err(render_error)?;
+    for (index, page_boxes) in boxes.iter_mut().enumerate() {
+        if page_boxes.is_empty() {
+            let image = document
+                .pages()
+                .get(index as u16)
+                .and_then(|page| app::render_page(&page))
+                .map_err(render_error)?;
+            *page_boxes = boxes_from_lines(&bitmap_lines(&image.to_luma8()));
+        }
+    }
+    Ok(boxes)
+}
+
+/// Detected box the user can accept as a field.
+#[derive(Debug, Clone)]
+pub struct FieldCandidate {
+    pub page: usize,
+    /// In points from the top left corner of the page.
+    pub rect: Rect,
+    pub kind: PdfInputFieldKind,
+    pub accepted: bool,
+}
+
+/// Boxes which are not mostly covered by a field of `layout` yet.
+fn candidates(boxes: Vec<Vec<DetectedBox>>, layout: &Layout) -> Vec<FieldCandidate> {
+    let mut candidates = Vec::new();
+    for (page, page_boxes) in boxes.into_iter().enumerate() {
+        let fields: Vec<Rect> = layout.pages.get(page).map_or_else(Vec::new, |layout_page| {
+            layout_page
+                .fields
+                .iter()
+                .map(|field| {
+                    Rect::from_min_size(
+                        pos2(field.pos_x, field.pos_y),
+                        egui::vec2(field.width, field.height),
+                    )
+                })
+                .collect()
+        });
+        candidates.extend(
+            page_boxes
+                .into_iter()
+                .filter(|detected| {
+                    let rect = detected.rect;
+                    !fields
+                        .iter()
+                        .any(|field| field.intersect(rect).area() > rect.area() * MAX_FIELD_OVERLAP)
+                })
+                .map(|detected| FieldCandidate {
+                    page,
+                    rect: detected.rect,
+                    kind: detected.kind,
+                    accepted: true,
+                }),
+        );
+    }
+    candidates
+}
+
+/// Review of the detected boxes before they become fields.
+pub struct FieldDetectionWindow {
+    pub open: bool,
+    receiver: Option<mpsc::Receiver<Result<Vec<FieldCandidate>, String>>>,
+    candidates: Result<Vec<FieldCandidate>, String>,
+}
+
+impl Default for FieldDetectionWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            receiver: None,
+            candidates: Ok(Vec::new()),
+        }
+    }
+}
+
+impl FieldDetectionWindow {
+    /// Opens the window and detects the boxes of the pdf at `pdf_path` which `layout` has no
+    /// field for yet on another thread.
+    pub fn start(&mut self, pdf_path: PathBuf, guides: Vec<SnapGuides>, layout: Layout) {
+        let (producer, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let candidates =
+                detect_boxes(&pdf_path, &guides).map(|boxes| candidates(boxes, &layout));
+            producer.send(candidates).ok();
+        });
+        self.receiver = Some(receiver);
+        self.candidates = Ok(Vec::new());
+        self.open = true;
+    }
+
+    /// Takes the result of the detection thread, returns whether it is still running.
+    fn poll(&mut self) -> bool {
+        let Some(receiver) = &self.receiver else {
+            return false;
+        };
+        self.candidates = match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => {
+                Err("Error: Connection to the field detection was lost.".to_owned())
+            }
+            Ok(result) => result,
+        };
+        self.receiver = None;
+        false
+    }
+
+    /// Outlines the candidates of page `page` over `page_rect`, the page's rect on screen.
+    pub fn paint(&self, painter: &egui::Painter, page_rect: Rect, page: usize) {
+        let Ok(candidates) = &self.candidates else {
+            return;
+        };
+        for candidate in candidates.iter().filter(|candidate| candidate.page == page) {
+            let color = if candidate.accepted {
+                Color32::from_rgb(0, 170, 0)
+            } else {
+                Color32::GRAY
+            };
+            painter.rect_stroke(
+                candidate.rect.translate(page_rect.min.to_vec2()),
+                0.0,
+                Stroke::new(1.5, color),
+                StrokeKind::Inside,
+            );
+        }
+    }
+
+    /// Shows the window, returns the accepted candidates when the user adds them.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Vec<FieldCandidate>> {
+        let mut open = self.open;
+        let mut add = false;
+        egui::Window::new("Detect fields")
+            .open(&mut open)
+            .default_width(300.)
+            .show(ctx, |ui| {
+                if self.poll() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Detecting boxes…");
+                    });
+                    return;
+                }
+                match &mut self.candidates {
+                    Ok(candidates) => add = ui_candidates(ui, candidates),
+                    Err(e) => {
+                        ui.label(e.as_str());
+                    }
+                }
+            });
+        self.open = open && !add;
+        if !self.open {
+            let candidates = std::mem::replace(&mut self.candidates, Ok(Vec::new()));
+            if add && let Ok(candidates) = candidates {
+                return Some(candidates.into_iter().filter(|c| c.accepted).collect());
+            }
+        }
+        None
+    }
+}
+
+/// List of the candidates, returns whether the accepted ones should be added.
+fn ui_candidates(ui: &mut egui::Ui, candidates: &mut [FieldCandidate]) -> bool {
+    let accepted = candidates.iter().filter(|c| c.accepted).count();
+    ui.label(format!(
+        "{} boxes found, {accepted} accepted. Green boxes on the pages are accepted.",
+        candidates.len()
+    ));
+    ui.horizontal(|ui| {
+        if ui.button("Accept all").clicked() {
+            candidates.iter_mut().for_each(|c| c.accepted = true);
+        }
+        if ui.button("Reject all").clicked() {
+            candidates.iter_mut().for_each(|c| c.accepted = false);
+        }
+    });
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .show(ui, |ui| {
+            egui::Grid::new("field_candidates")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("add");
+                    ui.strong("page");
+                    ui.strong("x");
+                    ui.strong("y");
+                    ui.strong("width");
+                    ui.strong("height");
+                    ui.strong("kind");
+                    ui.end_row();
+                    for candidate in candidates.iter_mut() {
+                        ui.checkbox(&mut candidate.accepted, "");
+                        ui.label((candidate.page + 1).to_string());
+                        let rect = candidate.rect;
+                        for value in [rect.left(), rect.top(), rect.width(), rect.height()] {
+                            ui.label(format!("{value:.1}"));
+                        }
+                        match candidate.kind {
+                            PdfInputFieldKind::Comb { cells } => {
+                                ui.label(format!("comb of {cells}"))
+                            }
+                            _ => ui.label("text"),
+                        };
+                        ui.end_row();
+                    }
+                });
+        });
+    ui.separator();
+    ui.add_enabled(
+        accepted > 0,
+        egui::Button::new(format!("Add {accepted} fields")),
+    )
+    .clicked()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn horizontal(y: f32, from_x: f32, to_x: f32) -> SnapLine {
+        SnapLine {
+            from: pos2(from_x, y),
+            to: pos2(to_x, y),
+        }
+    }
+
+    fn vertical(x: f32, from_y: f32, to_y: f32) -> SnapLine {
+        SnapLine {
+            from: pos2(x, from_y),
+            to: pos2(x, to_y),
+        }
+    }
+
+    #[test]
+    fn merges_the_rows_of_a_thick_line() {
+        let lines = vec![
+            horizontal(100.5, 10.0, 200.0),
+            horizontal(99.5, 12.0, 210.0),
+            horizontal(101.5, 5.0, 150.0),
+        ];
+        let merged = merge_lines(lines, true);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].from.x, 5.0);
+        assert_eq!(merged[0].to.x, 210.0);
+        assert!((merged[0].from.y - 100.5).abs() < LINE_TOLERANCE);
+    }
+
+    #[test]
+    fn keeps_separate_lines() {
+        // apart across the lines, and in line but with a gap along them
+        let lines = vec![
+            vertical(10.0, 0.0, 50.0),
+            vertical(20.0, 0.0, 50.0),
+            vertical(10.0, 60.0, 90.0),
+        ];
+        let merged = merge_lines(lines, false);
+        assert_eq!(merged.len(), 3);
+    }
+
+    #[test]
+    fn detects_a_box_and_a_comb() {
+        // a 100 x 20 box and below it a comb of four 15 x 20 cells
+        let mut guides = SnapGuides::default();
+        for y in [10.0, 30.0] {
+            guides.add_line(pos2(10.0, y), pos2(110.0, y));
+        }
+        for x in [10.0, 110.0] {
+            guides.add_line(pos2(x, 10.0), pos2(x, 30.0));
+        }
+        for y in [50.0, 70.0] {
+            guides.add_line(pos2(10.0, y), pos2(70.0, y));
+        }
+        for x in [10.0, 25.0, 40.0, 55.0, 70.0] {
+            guides.add_line(pos2(x, 50.0), pos2(x, 70.0));
+        }
+        let boxes = boxes_from_lines(&guides);
+        assert_eq!(
+            boxes,
+            vec![
+                DetectedBox {
+                    rect: Rect::from_min_max(pos2(10.0, 10.0), pos2(110.0, 30.0)),
+                    kind: PdfInputFieldKind::Text,
+                },
+                DetectedBox {
+                    rect: Rect::from_min_max(pos2(10.0, 50.0), pos2(70.0, 70.0)),
+                    kind: PdfInputFieldKind::Comb { cells: 4 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_table_columns_apart() {
+        let cells = [
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(60.0, 20.0)),
+            Rect::from_min_max(pos2(60.0, 0.0), pos2(120.0, 20.0)),
+        ];
+        let boxes = combine_cells(&cells);
+        assert_eq!(boxes.len(), 2);
+        assert!(
+            boxes
+                .iter()
+                .all(|detected| detected.kind == PdfInputFieldKind::Text)
+        );
+    }
+}
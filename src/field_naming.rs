@@ -0,0 +1,234 @@
+// field_naming.rs
+
+use std::collections::BTreeSet;
+
+use egui::Rect;
+
+use crate::text_layer::TextWord;
+
+/// Labels further left of a field than this in points belong to something else.
+const MAX_LEFT_DISTANCE: f32 = 150.0;
+
+/// Labels further above a field than this in points belong to something else.
+const MAX_ABOVE_DISTANCE: f32 = 25.0;
+
+/// Longer labels are cut to this many words.
+const MAX_LABEL_WORDS: usize = 6;
+
+/// Words introducing the number of a form line, e.g. `Zeile 12`.
+const LINE_NUMBER_WORDS: [&str; 4] = ["zeile", "line", "nr", "nr."];
+
+/// `text` as an identifier: lower case ascii letters, digits and single underscores.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let replacement = match c {
+            'ä' => "ae",
+            'ö' => "oe",
+            'ü' => "ue",
+            'ß' => "ss",
+            c if c.is_ascii_alphanumeric() => {
+                slug.push(c);
+                continue;
+            }
+            _ => "_",
+        };
+        if replacement == "_" && (slug.is_empty() || slug.ends_with('_')) {
+            continue;
+        }
+        slug.push_str(replacement);
+    }
+    slug.trim_end_matches('_').to_owned()
+}
+
+fn same_line(a: Rect, b: Rect) -> bool {
+    (a.center().y - b.center().y).abs() < a.height().max(b.height()) / 2.0
+}
+
+/// Words of the label ending next to the field on its left, in reading order.
+fn left_label(words: &[TextWord], field: Rect) -> Option<(f32, Vec<&TextWord>)> {
+    let mut row: Vec<&TextWord> = words
+        .iter()
+        .filter(|word| same_line(word.rect, field) && word.rect.right() <= field.left() + 1.0)
+        .collect();
+    row.sort_by(|a, b| b.rect.right().total_cmp(&a.rect.right()));
+    let nearest = row.first()?;
+    let distance = field.left() - nearest.rect.right();
+    if distance > MAX_LEFT_DISTANCE {
+        return None;
+    }
+    // walk left while the words follow each other
+    let mut label = vec![*nearest];
+    for pair in row.windows(2) {
+        let &[right, left] = pair else {
+            continue;
+        };
+        let gap = right.rect.left() - left.rect.right();
+        if gap > right.rect.height() * 1.5 || label.len() == MAX_LABEL_WORDS {
+            break;
+        }
+        label.push(left);
+    }
+    label.reverse();
+    Some((distance, label))
+}
+
+/// Words of the line right above the field which overlap it horizontally.
+fn label_above(words: &[TextWord], field: Rect) -> Option<(f32, Vec<&TextWord>)> {
+    let above = |word: &&TextWord| {
+        let distance = field.top() - word.rect.bottom();
+        (-1.0..=MAX_ABOVE_DISTANCE).contains(&distance)
+            && word.rect.right() > field.left()
+            && word.rect.left() < field.right()
+    };
+    let nearest = words
+        .iter()
+        .filter(above)
+        .max_by(|a, b| a.rect.bottom().total_cmp(&b.rect.bottom()))?;
+    let mut label: Vec<&TextWord> = words
+        .iter()
+        .filter(above)
+        .filter(|word| same_line(word.rect, nearest.rect))
+        .collect();
+    label.sort_by(|a, b| a.rect.left().total_cmp(&b.rect.left()));
+    label.truncate(MAX_LABEL_WORDS);
+    Some(((field.top() - nearest.rect.bottom()).max(0.0), label))
+}
+
+/// `Zeile 12` or similar on the same line as the field.
+fn line_number(words: &[TextWord], field: Rect) -> Option<String> {
+    words
+        .windows(2)
+        .filter(|pair| pair.iter().all(|word| same_line(word.rect, field)))
+        .find_map(|pair| {
+            let [name, number] = pair else {
+                return None;
+            };
+            let is_line_word = LINE_NUMBER_WORDS.contains(&name.text.to_lowercase().as_str());
+            let is_number = number.text.chars().all(|c| c.is_ascii_digit());
+            (is_line_word && is_number).then(|| format!("{} {}", name.text, number.text))
+        })
+}
+
+/// Identifier for a field at `field` from the closest label left of or above it, or the
+/// number of its form line if the label has no letters.
+pub fn suggest_name(words: &[TextWord], field: Rect) -> Option<String> {
+    let label = match (left_label(words, field), label_above(words, field)) {
+        (Some(left), Some(above)) => Some(if above.0 < left.0 { above } else { left }),
+        (left, above) => left.or(above),
+    };
+    let label = label
+        .map(|(_, words)| {
+            let texts: Vec<&str> = words.iter().map(|word| word.text.as_str()).collect();
+            slugify(&texts.join(" "))
+        })
+        .filter(|slug| slug.chars().any(|c| c.is_ascii_alphabetic()));
+    label.or_else(|| line_number(words, field).map(|number| slugify(&number)))
+}
+
+/// `name`, or `name` with the lowest number appended which is not in `used` yet.
+pub fn unique_name(name: &str, used: &mut BTreeSet<String>) -> String {
+    let mut unique = name.to_owned();
+    let mut number = 2;
+    while used.contains(&unique) {
+        unique = format!("{name}_{number}");
+        number += 1;
+    }
+    used.insert(unique.clone());
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, x: f32, y: f32) -> TextWord {
+        TextWord {
+            text: text.to_owned(),
+            rect: Rect::from_min_size(egui::pos2(x, y), egui::vec2(8.0 * text.len() as f32, 10.0)),
+        }
+    }
+
+    fn field(x: f32, y: f32) -> Rect {
+        Rect::from_min_size(egui::pos2(x, y), egui::vec2(120.0, 10.0))
+    }
+
+    #[test]
+    fn slugify_collapses_separators() {
+        assert_eq!(slugify("Vor- und Zuname:"), "vor_und_zuname");
+        assert_eq!(slugify("  (E-Mail)  "), "e_mail");
+        assert_eq!(slugify("Zeile 12"), "zeile_12");
+    }
+
+    #[test]
+    fn slugify_transliterates_umlauts() {
+        assert_eq!(slugify("Straße, Hausnr."), "strasse_hausnr");
+        assert_eq!(slugify("ÄÖÜ äöü"), "aeoeue_aeoeue");
+    }
+
+    #[test]
+    fn slugify_without_letters_or_digits_is_empty() {
+        assert_eq!(slugify(""), "");
+        assert_eq!(slugify(" -: "), "");
+    }
+
+    #[test]
+    fn unique_name_appends_the_lowest_free_number() {
+        let mut used = BTreeSet::from(["name".to_owned(), "name_2".to_owned()]);
+        assert_eq!(unique_name("name", &mut used), "name_3");
+        assert_eq!(unique_name("city", &mut used), "city");
+        assert!(used.contains("name_3") && used.contains("city"));
+    }
+
+    #[test]
+    fn suggests_the_label_left_of_the_field() {
+        // "Kunde" is too far left to belong to the label
+        let words = [
+            word("Kunde", 0., 100.),
+            word("Vor-", 100., 100.),
+            word("und", 136., 100.),
+            word("Zuname:", 164., 100.),
+        ];
+        assert_eq!(
+            suggest_name(&words, field(240., 100.)).as_deref(),
+            Some("vor_und_zuname")
+        );
+        assert_eq!(suggest_name(&words, field(600., 100.)), None);
+    }
+
+    #[test]
+    fn suggests_the_label_above_the_field() {
+        let words = [word("Straße,", 100., 80.), word("Hausnr.", 160., 80.)];
+        assert_eq!(
+            suggest_name(&words, field(90., 95.)).as_deref(),
+            Some("strasse_hausnr")
+        );
+        assert_eq!(suggest_name(&words, field(400., 95.)), None);
+    }
+
+    #[test]
+    fn suggests_the_nearer_label() {
+        let words = [word("Ort", 100., 80.), word("PLZ", 20., 100.)];
+        assert_eq!(
+            suggest_name(&words, field(100., 92.)).as_deref(),
+            Some("ort")
+        );
+        assert_eq!(
+            suggest_name(&words, field(50., 100.)).as_deref(),
+            Some("plz")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_line_number() {
+        let words = [
+            word("Zeile", 0., 100.),
+            word("12", 48., 100.),
+            word("1.234", 150., 100.),
+        ];
+        assert_eq!(
+            suggest_name(&words, field(200., 100.)).as_deref(),
+            Some("zeile_12")
+        );
+    }
+}
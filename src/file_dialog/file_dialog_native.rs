@@ -3,8 +3,13 @@
 use egui::Popup;
 use pdfium_render::prelude::Pdfium;
 
-use crate::app::{PdfLoadError, create_images_from_pdf};
-use crate::{PdfCoordPickerApp, pdf_load};
+use crate::app::{PdfFileLoadType, PdfLoadError, load_pdf_document};
+use crate::exporter::{self, ExportContext};
+use crate::json;
+use crate::project::{self, PROJECT_FILE_EXTENSION};
+use crate::{
+    PdfCoordPickerApp, anchor, batch_extraction, extraction, form_data, pdf_load, pdftk_dump,
+};
 use std::borrow::Cow;
 use std::path::PathBuf;
 use std::sync::mpsc::TryRecvError;
@@ -26,6 +31,64 @@ pub fn handle_open_file_dialog_native(
                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             }
         }
+        ui.separator();
+        if ui.button("Open project…").clicked() {
+            open_project(app, ctx);
+        }
+        if ui.button("Save project").clicked() {
+            save_project(app, false);
+        }
+        if ui.button("Save project as…").clicked() {
+            save_project(app, true);
+        }
+        let library_button = ui.button("Layout library folder…");
+        let library_button = match &app.layout_library_dir {
+            Some(directory) => library_button.on_hover_text(directory.to_string_lossy()),
+            None => library_button,
+        };
+        if library_button.clicked()
+            && let Some(directory) = rfd::FileDialog::new().pick_folder()
+        {
+            app.layout_library_dir = Some(directory);
+        }
+        ui.checkbox(&mut app.layout_library.open, "Show layout library");
+        if ui.button("Migrate layout to new revision…").clicked() {
+            start_revision_migration(app);
+        }
+        if ui.button("Compare with other revision…").clicked() {
+            start_compare(app);
+        }
+        if ui.button("Onion skin overlay…").clicked() {
+            app.onion_skin.open = true;
+        }
+        if ui.button("Detect fields…").clicked() {
+            start_field_detection(app);
+        }
+        ui.menu_button("Export", |ui| {
+            export_menu(app, ui);
+            ui.separator();
+            if ui.button("Mail merge from csv…").clicked() {
+                app.mail_merge.open = true;
+            }
+            if ui.button("Extract pdf directory to csv…").clicked() {
+                spawn_batch_extraction_thread(app);
+            }
+        });
+        ui.menu_button("Import", |ui| {
+            if ui.button("Values from json…").clicked() {
+                import_json_values(app);
+            }
+            if ui.button("Values from xfdf…").clicked() {
+                import_xfdf_values(app);
+            }
+            if ui.button("Values from filled pdf…").clicked() {
+                import_values_from_filled_pdf(app);
+            }
+            ui.separator();
+            if ui.button("Fields from pdftk dump…").clicked() {
+                import_pdftk_dump(app);
+            }
+        });
     });
     if app.waiting_for_file {
         handle_file_load_from_dialog_thread(app, ctx, ui);
@@ -46,9 +109,338 @@ fn spawn_file_dialog_thread(app: &mut PdfCoordPickerApp) {
     });
 }
 
-pub fn load_pdf_file_from_filesystem(
-    path: PathBuf,
-) -> Result<(PathBuf, Vec<image::DynamicImage>), PdfLoadError> {
+/// Loads the pdf at `path` on another thread, the result arrives like the one of the file dialog.
+pub fn spawn_pdf_load_thread(app: &mut PdfCoordPickerApp, path: PathBuf) {
+    app.waiting_for_file = true;
+    let mp = app.producer.clone();
+    std::thread::spawn(move || {
+        mp.send(load_pdf_file_from_filesystem(path)).ok();
+    });
+}
+
+/// Lets the user pick a destination and writes `bytes` to it without blocking the ui.
+pub fn spawn_save_file_dialog(file_name: &str, bytes: Vec<u8>) {
+    let file_name = file_name.to_owned();
+    std::thread::spawn(move || {
+        if let Some(path) = rfd::FileDialog::new().set_file_name(file_name).save_file()
+            && let Err(e) = std::fs::write(&path, bytes)
+        {
+            log::error!("Could not write file='{}': {e}", path.to_string_lossy());
+        }
+    });
+}
+
+fn export_menu(app: &mut PdfCoordPickerApp, ui: &mut egui::Ui) {
+    let mut layout = app.layout();
+    let anchor_warnings = anchor::resolve_anchors(&mut layout, &app.page_words);
+    let calibration = app.active_calibration().cloned();
+    let context = ExportContext {
+        pdf_file_path: app.pdf_file_path.as_deref(),
+        calibration: calibration.as_ref(),
+    };
+    match exporter::export_menu(ui, &mut app.exporters, &layout, &context) {
+        Some(Ok((file_name, bytes))) => {
+            spawn_save_file_dialog(&file_name, bytes);
+            app.status_message = (!anchor_warnings.is_empty()).then(|| {
+                format!(
+                    "Exported with warnings:{}",
+                    anchor::warning_list(&anchor_warnings)
+                )
+            });
+        }
+        Some(Err(e)) => app.status_message = Some(format!("Could not export: {e}")),
+        None => {}
+    }
+}
+
+fn open_project(app: &mut PdfCoordPickerApp, ctx: &egui::Context) {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("project", &[PROJECT_FILE_EXTENSION])
+        .pick_file()
+    else {
+        return;
+    };
+    let (project, migrations) = match project::load_project(&path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            app.status_message = Some(format!(
+                "Could not open project file='{}': {e}",
+                path.to_string_lossy()
+            ));
+            return;
+        }
+    };
+    let pdf_path = project.pdf_path.clone();
+    let pdf_bytes = std::fs::read(&pdf_path);
+    match load_pdf_file_from_filesystem(pdf_path.clone()) {
+        Ok((pdf_path, loaded_pdf)) => {
+            app.pdf_file_path = Some(pdf_path);
+            app.init_loaded_pdf(ctx, loaded_pdf);
+            let mut messages = Vec::new();
+            if !migrations.is_empty() {
+                messages.push(format!(
+                    "Upgraded the project file, save it to keep the new format:\n- {}",
+                    migrations.join("\n- ")
+                ));
+            }
+            if pdf_bytes.is_ok_and(|bytes| project::content_hash(&bytes) != project.pdf_hash) {
+                messages.push(format!(
+                    "The pdf file='{}' changed since the project was saved, check the field positions.",
+                    project.pdf_path.to_string_lossy()
+                ));
+            }
+            let anchor_warnings = app.apply_project(project);
+            if !anchor_warnings.is_empty() {
+                messages.push(format!(
+                    "Anchor warnings:{}",
+                    anchor::warning_list(&anchor_warnings)
+                ));
+            }
+            app.status_message = (!messages.is_empty()).then(|| messages.join("\n"));
+            app.mark_project_saved(path);
+        }
+        Err(_) => {
+            app.status_message = Some(format!(
+                "Could not load the pdf file='{}' of the project.",
+                pdf_path.to_string_lossy()
+            ));
+        }
+    }
+}
+
+/// Saves to the path the project was opened from, asks for one with `save_as` or if there is none.
+fn save_project(app: &mut PdfCoordPickerApp, save_as: bool) {
+    let Some(mut project) = app.project() else {
+        app.status_message = Some("Open a pdf file before saving a project.".to_owned());
+        return;
+    };
+    let path = if let Some(path) = app.project_path.clone().filter(|_| !save_as) {
+        path
+    } else {
+        let file_name = project.pdf_path.with_extension(PROJECT_FILE_EXTENSION);
+        let file_name = file_name
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("project", &[PROJECT_FILE_EXTENSION])
+            .set_file_name(file_name)
+            .save_file()
+        else {
+            return;
+        };
+        path
+    };
+    project.pdf_hash = std::fs::read(&project.pdf_path)
+        .map(|bytes| project::content_hash(&bytes))
+        .unwrap_or_default();
+    match project::save_project(&path, &project) {
+        Ok(()) => {
+            app.mark_project_saved(path);
+            app.status_message = None;
+        }
+        Err(e) => {
+            app.status_message = Some(format!(
+                "Could not save project file='{}': {e}",
+                path.to_string_lossy()
+            ));
+        }
+    }
+}
+
+fn start_revision_migration(app: &mut PdfCoordPickerApp) {
+    let Some(old_pdf_path) = app.pdf_file_path.clone() else {
+        app.status_message = Some("Open the pdf of the layout to migrate first.".to_owned());
+        return;
+    };
+    let Some(new_pdf_path) = rfd::FileDialog::new()
+        .add_filter("pdf", &["pdf"])
+        .pick_file()
+    else {
+        return;
+    };
+    let layout = app.layout();
+    app.revision_migration
+        .start(layout, old_pdf_path, new_pdf_path);
+}
+
+fn start_compare(app: &mut PdfCoordPickerApp) {
+    let Some(current_pdf_path) = app.pdf_file_path.clone() else {
+        app.status_message = Some("Open a pdf to compare first.".to_owned());
+        return;
+    };
+    if let Some(other_pdf_path) = rfd::FileDialog::new()
+        .add_filter("pdf", &["pdf"])
+        .pick_file()
+    {
+        app.compare.start(current_pdf_path, other_pdf_path);
+    }
+}
+
+fn start_field_detection(app: &mut PdfCoordPickerApp) {
+    let Some(pdf_path) = app.pdf_file_path.clone() else {
+        app.status_message = Some("Open a pdf to detect fields in first.".to_owned());
+        return;
+    };
+    let layout = app.layout();
+    app.field_detection.start(pdf_path, app.snap_guides.clone(), layout);
+}
+
+fn import_json_values(app: &mut PdfCoordPickerApp) {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("json", &["json"])
+        .pick_file()
+    else {
+        return;
+    };
+    let records = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+        .and_then(|value| json::records_from_json(&value));
+    match records {
+        Ok(records) => app.set_value_records(records),
+        Err(e) => {
+            app.status_message = Some(format!(
+                "Could not import file='{}': {e}",
+                path.to_string_lossy()
+            ));
+        }
+    }
+}
+
+fn import_xfdf_values(app: &mut PdfCoordPickerApp) {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("xfdf", &["xfdf"])
+        .pick_file()
+    else {
+        return;
+    };
+    let values = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| form_data::parse_xfdf(&text).map_err(|e| e.to_string()));
+    match values {
+        Ok(values) => {
+            let (values, unknown) = form_data::values_for_layout(values, &app.layout());
+            app.set_value_records(vec![values]);
+            if !unknown.is_empty() {
+                app.status_message = Some(format!(
+                    "No input fields for xfdf fields: {}",
+                    unknown.join(", ")
+                ));
+            }
+        }
+        Err(e) => {
+            app.status_message = Some(format!(
+                "Could not import file='{}': {e}",
+                path.to_string_lossy()
+            ));
+        }
+    }
+}
+
+fn spawn_batch_extraction_thread(app: &mut PdfCoordPickerApp) {
+    let layout = app.layout();
+    let (producer, receiver) = std::sync::mpsc::channel();
+    app.background_task = Some(receiver);
+    std::thread::spawn(move || {
+        let Some(directory) = rfd::FileDialog::new().pick_folder() else {
+            producer.send("No directory was selected.".to_owned()).ok();
+            return;
+        };
+        let message = match batch_extraction::extract_directory(&directory, &layout) {
+            Ok(table) => {
+                let file_count = table.rows.len();
+                match rfd::FileDialog::new()
+                    .set_file_name("extraction.csv")
+                    .save_file()
+                {
+                    Some(path) => match std::fs::write(&path, table.to_csv_string()) {
+                        Ok(()) => format!("Extracted {file_count} pdf files."),
+                        Err(e) => {
+                            format!("Could not write file='{}': {e}", path.to_string_lossy())
+                        }
+                    },
+                    None => "Extraction was not saved.".to_owned(),
+                }
+            }
+            Err(e) => format!(
+                "Could not read directory='{}': {e}",
+                directory.to_string_lossy()
+            ),
+        };
+        producer.send(message).ok();
+    });
+}
+
+fn import_values_from_filled_pdf(app: &mut PdfCoordPickerApp) {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("pdf", &["pdf"])
+        .pick_file()
+    else {
+        return;
+    };
+    let pdfium = Pdfium::default();
+    let mut layout = app.layout();
+    let values = pdf_load::load_pdf_native(&pdfium, &path).and_then(|document| {
+        let anchor_warnings = anchor::resolve_document_anchors(&mut layout, &document)?;
+        Ok((
+            extraction::extract_fields(&document, &layout)?,
+            anchor_warnings,
+        ))
+    });
+    match values {
+        Ok((extraction, anchor_warnings)) => {
+            app.set_value_records(vec![extraction.values]);
+            if !anchor_warnings.is_empty() {
+                let filled = app.status_message.take().unwrap_or_default();
+                app.status_message = Some(format!(
+                    "{filled}\nAnchor warnings:{}",
+                    anchor::warning_list(&anchor_warnings)
+                ));
+            }
+        }
+        Err(e) => {
+            app.status_message = Some(format!(
+                "Could not extract values from file='{}': {e}",
+                path.to_string_lossy()
+            ));
+        }
+    }
+}
+
+fn import_pdftk_dump(app: &mut PdfCoordPickerApp) {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("pdftk dump_data_fields", &["txt"])
+        .pick_file()
+    else {
+        return;
+    };
+    let fields = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| pdftk_dump::parse_dump_data_fields(&text).map_err(|e| e.to_string()));
+    match fields {
+        Ok(fields) => {
+            let mut layout = app.layout();
+            for page in &mut layout.pages {
+                page.fields.clear();
+            }
+            let skipped = pdftk_dump::add_dumped_fields(&mut layout, &fields);
+            let (added, _) = app.add_input_fields(&layout);
+            app.status_message = Some(format!(
+                "Imported {added} fields, skipped {skipped} buttons, signatures \
+                 and fields without rect or page."
+            ));
+        }
+        Err(e) => {
+            app.status_message = Some(format!(
+                "Could not import file='{}': {e}",
+                path.to_string_lossy()
+            ));
+        }
+    }
+}
+
+pub fn load_pdf_file_from_filesystem(path: PathBuf) -> PdfFileLoadType {
     if let Ok(true) = std::fs::exists(&path) {
         load_pdf_file(path)
     } else {
@@ -56,10 +448,13 @@ pub fn load_pdf_file_from_filesystem(
     }
 }
 
-fn load_pdf_file(path: PathBuf) -> Result<(PathBuf, Vec<image::DynamicImage>), PdfLoadError> {
+fn load_pdf_file(path: PathBuf) -> PdfFileLoadType {
+    let content_hash = std::fs::read(&path)
+        .map(|bytes| project::content_hash(&bytes))
+        .unwrap_or_default();
     match pdf_load::load_pdf_native(&Pdfium::default(), &path) {
-        Ok(pdf_document) => match create_images_from_pdf(pdf_document) {
-            Ok(pdf_images) => Ok((path, pdf_images)),
+        Ok(pdf_document) => match load_pdf_document(pdf_document, content_hash) {
+            Ok(loaded_pdf) => Ok((path, loaded_pdf)),
             Err(e) => Err(PdfLoadError::PdfError((path, e))),
         },
         Err(e) => Err(PdfLoadError::PdfError((path, e))),
@@ -70,18 +465,24 @@ pub fn handle_file_load_result(
     app: &mut PdfCoordPickerApp,
     ctx: &egui::Context,
     ui: &mut egui::Ui,
-    result: Result<(PathBuf, Vec<image::DynamicImage>), PdfLoadError>,
+    result: PdfFileLoadType,
 ) {
     match result {
-        Ok((path, page_images)) => {
-            app.pdf_file_path = Some(path);
+        Ok((path, loaded_pdf)) => {
+            app.pdf_file_path = Some(path.clone());
             app.waiting_for_file = false;
-            app.init_pdf_page_images(ctx, page_images)
+            app.init_loaded_pdf(ctx, loaded_pdf);
+            if !app.apply_restored_session(&path) {
+                app.match_layout_library(ctx);
+            }
         }
         //TODO: ui elements need some file load state to be actually displayed for
         //longer
         Err(e) => {
             app.waiting_for_file = false;
+            if let PdfLoadError::PdfError((path, _)) = &e {
+                app.discard_restored_session(path);
+            }
             let err_text = match e {
                 PdfLoadError::FileError => Cow::Borrowed("Could not open file"),
                 PdfLoadError::PdfError((path, e)) => Cow::Owned(format!(
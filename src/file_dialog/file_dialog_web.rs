@@ -3,7 +3,7 @@
 use pdfium_render::prelude::Pdfium;
 use pdfium_render::prelude::PdfiumError;
 
-use crate::app::{PdfLoadError, create_images_from_pdf};
+use crate::app::{PdfLoadError, load_pdf_document};
 use crate::{PdfCoordPickerApp, pdf_load};
 use std::sync::mpsc::TryRecvError;
 
@@ -50,8 +50,8 @@ pub fn handle_open_file_dialog_web(
                     pdf_load::load_pdf_web(&Pdfium::default(), Blob::from(file.inner().clone()))
                         .await
                 {
-                    let result = match create_images_from_pdf(pdf_document) {
-                        Ok(pdf_images) => Ok(("".into(), pdf_images)),
+                    let result = match load_pdf_document(pdf_document, String::new()) {
+                        Ok(loaded_pdf) => Ok(("".into(), loaded_pdf)),
                         Err(e) => Err(PdfLoadError::PdfError(("".into(), e))),
                     };
                     let _ = mp.send(result);
@@ -0,0 +1,162 @@
+// fingerprint.rs
+
+use std::collections::BTreeSet;
+
+use pdfium_render::prelude::{PdfDocument, PdfDocumentMetadataTagType, PdfiumError};
+use serde::{Deserialize, Serialize};
+
+/// Characters of the first page text kept, enough to tell form types apart.
+const FIRST_PAGE_TEXT_LENGTH: usize = 2000;
+
+/// Page sizes may differ this much in points between revisions of the same form.
+const PAGE_SIZE_TOLERANCE: f32 = 1.0;
+
+/// Features of a pdf document to recognize other copies and revisions of the same form.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Fingerprint {
+    /// Hash of the file contents, empty if the file was not available.
+    pub content_hash: String,
+    /// Width and height of every page in points.
+    pub page_sizes: Vec<[f32; 2]>,
+    pub title: String,
+    /// Start of the text of the first page with whitespace collapsed.
+    pub first_page_text: String,
+}
+
+impl Fingerprint {
+    pub fn from_document(
+        document: &PdfDocument<'_>,
+        content_hash: String,
+    ) -> Result<Self, PdfiumError> {
+        let page_sizes = document
+            .pages()
+            .page_sizes()?
+            .iter()
+            .map(|rect| [rect.width().value, rect.height().value])
+            .collect();
+        let title = document
+            .metadata()
+            .get(PdfDocumentMetadataTagType::Title)
+            .map(|tag| tag.value().trim().to_owned())
+            .unwrap_or_default();
+        let first_page_text = match document.pages().first() {
+            Ok(page) => page.text()?.all(),
+            Err(_) => String::new(),
+        };
+        let first_page_text = first_page_text
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(FIRST_PAGE_TEXT_LENGTH)
+            .collect();
+        Ok(Self {
+            content_hash,
+            page_sizes,
+            title,
+            first_page_text,
+        })
+    }
+
+    /// How likely `other` is the same form, from 0 to 1.
+    ///
+    /// Identical files score 1. Otherwise the page count and sizes have to agree, and the
+    /// score grows with a matching title and the words both first pages share.
+    pub fn match_score(&self, other: &Self) -> f32 {
+        if !self.content_hash.is_empty() && self.content_hash == other.content_hash {
+            return 1.0;
+        }
+        let same_pages = self.page_sizes.len() == other.page_sizes.len()
+            && self
+                .page_sizes
+                .iter()
+                .zip(&other.page_sizes)
+                .all(|([w1, h1], [w2, h2])| {
+                    (w1 - w2).abs() <= PAGE_SIZE_TOLERANCE && (h1 - h2).abs() <= PAGE_SIZE_TOLERANCE
+                });
+        if !same_pages {
+            return 0.0;
+        }
+        let same_title = !self.title.is_empty() && self.title.eq_ignore_ascii_case(&other.title);
+        let title_score = if same_title { 0.2 } else { 0.0 };
+        0.25 + title_score + 0.5 * word_similarity(&self.first_page_text, &other.first_page_text)
+    }
+}
+
+/// Share of the distinct words of both texts that appear in both, 0 if both are empty.
+fn word_similarity(a: &str, b: &str) -> f32 {
+    let words = |text: &str| -> BTreeSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout_library::MATCH_THRESHOLD;
+
+    fn fingerprint(content_hash: &str, pages: usize, title: &str, text: &str) -> Fingerprint {
+        Fingerprint {
+            content_hash: content_hash.to_owned(),
+            page_sizes: vec![[595.0, 842.0]; pages],
+            title: title.to_owned(),
+            first_page_text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn identical_files_match_fully() {
+        let a = fingerprint("0123", 2, "", "");
+        let b = fingerprint("0123", 3, "other", "other text");
+        assert_eq!(a.match_score(&b), 1.0);
+    }
+
+    #[test]
+    fn different_pages_do_not_match() {
+        let a = fingerprint("", 2, "Tax form", "Income tax return 2024");
+        let b = fingerprint("", 3, "Tax form", "Income tax return 2024");
+        assert_eq!(a.match_score(&b), 0.0);
+        let mut resized = a.clone();
+        resized.page_sizes[1] = [612.0, 792.0];
+        assert_eq!(a.match_score(&resized), 0.0);
+    }
+
+    #[test]
+    fn same_form_revision_reaches_the_threshold() {
+        let a = fingerprint(
+            "0123",
+            2,
+            "Tax form",
+            "Income tax return 2024 name address date of birth signature",
+        );
+        let b = fingerprint(
+            "4567",
+            2,
+            "tax form",
+            "Income tax return 2025 name address date of birth signature",
+        );
+        assert!(
+            a.match_score(&b) >= MATCH_THRESHOLD,
+            "{}",
+            a.match_score(&b)
+        );
+    }
+
+    #[test]
+    fn same_pages_alone_stay_below_the_threshold() {
+        let a = fingerprint("0123", 2, "", "Income tax return");
+        let b = fingerprint("4567", 2, "", "Application for a parking permit");
+        assert_eq!(a.match_score(&b), 0.25);
+        assert!(a.match_score(&b) < MATCH_THRESHOLD);
+    }
+}
@@ -0,0 +1,249 @@
+// form_data.rs
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::acroform;
+use crate::layout::{FieldValues, Layout};
+use crate::pdf_objects::{PdfDictionary, PdfObject};
+use crate::pdf_text_input::{PdfInputFieldKind, PdfInputFieldSerde};
+
+#[derive(Debug)]
+pub enum XmlError {
+    Syntax(roxmltree::Error),
+    /// A `<field>` element has no `name` attribute, `line` starts at 1.
+    FieldWithoutName {
+        line: u32,
+    },
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(e) => write!(f, "{e}"),
+            Self::FieldWithoutName { line } => write!(f, "field without name at line {line}"),
+        }
+    }
+}
+
+/// Fields with their pdf form field name, named like the fields of a fillable pdf export.
+fn named_fields(layout: &Layout) -> Vec<(String, &PdfInputFieldSerde)> {
+    let mut used_names = BTreeSet::new();
+    layout
+        .fields()
+        .map(|field| {
+            let name = acroform::unique_field_name(&mut used_names, &field.unique_id);
+            (name, field)
+        })
+        .collect()
+}
+
+/// Value as the form field of a fillable pdf expects it, checkboxes are on or off.
+fn form_value(field: &PdfInputFieldSerde) -> &str {
+    match field.kind {
+        PdfInputFieldKind::Checkbox if PdfInputFieldKind::is_checked(&field.text) => "Yes",
+        PdfInputFieldKind::Checkbox => "Off",
+        PdfInputFieldKind::Text | PdfInputFieldKind::Comb { .. } => &field.text,
+    }
+}
+
+/// Writes the field values as fdf, optionally referring to the pdf file they belong to.
+pub fn to_fdf(layout: &Layout, pdf_file_name: Option<&str>) -> Vec<u8> {
+    let fields = named_fields(layout)
+        .into_iter()
+        .map(|(name, field)| {
+            // checkbox states are names, texts are strings
+            let value = if field.kind == PdfInputFieldKind::Checkbox {
+                PdfObject::name(form_value(field))
+            } else {
+                PdfObject::text(&field.text)
+            };
+            PdfObject::Dictionary(
+                PdfDictionary::new()
+                    .with("T", PdfObject::text(&name))
+                    .with("V", value),
+            )
+        })
+        .collect();
+    let mut fdf = PdfDictionary::new().with("Fields", PdfObject::Array(fields));
+    if let Some(pdf_file_name) = pdf_file_name {
+        fdf.insert("F", PdfObject::text(pdf_file_name));
+    }
+    let root = PdfDictionary::new().with("FDF", PdfObject::Dictionary(fdf));
+
+    let mut out = b"%FDF-1.2\n%\xe2\xe3\xcf\xd3\n1 0 obj\n".to_vec();
+    out.extend(PdfObject::Dictionary(root).to_bytes());
+    out.extend_from_slice(b"\nendobj\ntrailer\n<</Root 1 0 R>>\n%%EOF\n");
+    out
+}
+
+/// Writes the field values as xfdf, optionally referring to the pdf file they belong to.
+pub fn to_xfdf(layout: &Layout, pdf_file_name: Option<&str>) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <xfdf xmlns=\"http://ns.adobe.com/xfdf/\" xml:space=\"preserve\">\n",
+    );
+    if let Some(pdf_file_name) = pdf_file_name {
+        out.push_str(&format!("  <f href=\"{}\"/>\n", escape_xml(pdf_file_name)));
+    }
+    out.push_str("  <fields>\n");
+    for (name, field) in named_fields(layout) {
+        out.push_str(&format!(
+            "    <field name=\"{}\">\n      <value>{}</value>\n    </field>\n",
+            escape_xml(&name),
+            escape_xml(form_value(field))
+        ));
+    }
+    out.push_str("  </fields>\n</xfdf>\n");
+    out
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reads the `<field>` values of an xfdf document, nested fields are named `parent.child`.
+pub fn parse_xfdf(text: &str) -> Result<FieldValues, XmlError> {
+    let document = roxmltree::Document::parse(text).map_err(XmlError::Syntax)?;
+    let mut values = FieldValues::new();
+    for value in document
+        .descendants()
+        .filter(|node| node.has_tag_name("value"))
+    {
+        let mut names = Vec::new();
+        for field in value.ancestors().filter(|node| node.has_tag_name("field")) {
+            let Some(name) = field.attribute("name") else {
+                let line = document.text_pos_at(field.range().start).row;
+                return Err(XmlError::FieldWithoutName { line });
+            };
+            names.push(name);
+        }
+        names.reverse();
+        let text = value
+            .descendants()
+            .filter(roxmltree::Node::is_text)
+            .filter_map(|node| node.text())
+            .collect();
+        values.insert(names.join("."), text);
+    }
+    Ok(values)
+}
+
+/// Maps values keyed by pdf form field name back to the `unique_id`s of `layout`.
+///
+/// Returns the values of known fields and the names of unknown ones.
+pub fn values_for_layout(values: FieldValues, layout: &Layout) -> (FieldValues, Vec<String>) {
+    let named_fields = named_fields(layout);
+    let mut known = FieldValues::new();
+    let mut unknown = Vec::new();
+    for (name, value) in values {
+        match named_fields
+            .iter()
+            .find(|(field_name, _)| *field_name == name)
+        {
+            // fields without id cannot take a value
+            Some((_, field)) if field.unique_id.is_empty() => {}
+            Some((_, field)) => {
+                known.insert(field.unique_id.clone(), value);
+            }
+            None => unknown.push(name),
+        }
+    }
+    (known, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutPage;
+
+    #[test]
+    fn escape_markup() {
+        assert_eq!(escape_xml("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn xfdf_values_are_unescaped() {
+        let xfdf = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xfdf xmlns="http://ns.adobe.com/xfdf/" xml:space="preserve">
+  <!-- <field name="comment"> -->
+  <fields>
+    <field name="a>b"><value>x &amp; &#x41;&#66;&lt;&gt;&quot;&apos;</value></field>
+    <field name="cdata"><value><![CDATA[<raw> & ]]></value></field>
+    <field name="person">
+      <field name="name"><value>Ada</value></field>
+      <field name="empty"><value/></field>
+    </field>
+  </fields>
+</xfdf>"#;
+        let values = parse_xfdf(xfdf).unwrap();
+        assert_eq!(values.len(), 4, "{values:?}");
+        assert_eq!(values["a>b"], "x & AB<>\"'");
+        assert_eq!(values["cdata"], "<raw> & ");
+        assert_eq!(values["person.name"], "Ada");
+        assert_eq!(values["person.empty"], "");
+    }
+
+    #[test]
+    fn invalid_xfdf_is_rejected() {
+        let bare_ampersand =
+            r#"<xfdf><fields><field name="a"><value>a & b</value></field></fields></xfdf>"#;
+        assert!(matches!(
+            parse_xfdf(bare_ampersand),
+            Err(XmlError::Syntax(_))
+        ));
+        let unnamed = "<xfdf>\n<fields>\n<field><value>a</value></field></fields></xfdf>";
+        assert!(matches!(
+            parse_xfdf(unnamed),
+            Err(XmlError::FieldWithoutName { line: 3 })
+        ));
+    }
+
+    fn field(unique_id: &str, text: &str) -> PdfInputFieldSerde {
+        PdfInputFieldSerde {
+            unique_id: unique_id.to_owned(),
+            text: text.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn xfdf_round_trip_uses_fillable_pdf_names() {
+        let layout = Layout {
+            pages: vec![LayoutPage {
+                width: 200.,
+                height: 100.,
+                fields: vec![
+                    field("name", "Doe & Sons"),
+                    field("", "no id"),
+                    field("name", "second"),
+                    field("a.b", "<dotted>"),
+                ],
+            }],
+        };
+        let names: Vec<String> = named_fields(&layout)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["name", "field", "name_2", "a_b"]);
+
+        let values = parse_xfdf(&to_xfdf(&layout, Some("form.pdf"))).unwrap();
+        assert_eq!(values.len(), 4);
+        assert_eq!(values["name_2"], "second");
+        assert_eq!(values["a_b"], "<dotted>");
+        let (known, unknown) = values_for_layout(values, &layout);
+        assert!(unknown.is_empty(), "unknown fields {unknown:?}");
+        assert_eq!(known["a.b"], "<dotted>");
+        assert!(!known.contains_key(""));
+    }
+}
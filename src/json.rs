@@ -0,0 +1,77 @@
+// json.rs
+
+use serde_json::Value;
+
+use crate::layout::FieldValues;
+
+/// Text of a scalar value, as it would be typed into a field.
+fn field_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::String(value) => Some(value.clone()),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+/// Reads field values from a json object keyed by `unique_id`, or from an array of such objects.
+pub fn records_from_json(value: &Value) -> Result<Vec<FieldValues>, String> {
+    let record_from_object = |value: &Value| match value {
+        Value::Object(members) => members
+            .iter()
+            .map(|(key, value)| {
+                field_text(value)
+                    .map(|text| (key.clone(), text))
+                    .ok_or_else(|| format!("value of '{key}' is not a string, number or bool"))
+            })
+            .collect(),
+        _ => Err("expected an object keyed by field id".to_owned()),
+    };
+    match value {
+        Value::Array(values) => values.iter().map(record_from_object).collect(),
+        value => record_from_object(value).map(|record| vec![record]),
+    }
+}
+
+pub fn record_to_json(values: &FieldValues) -> Value {
+    Value::Object(
+        values
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_scalars_as_field_text() {
+        let value: Value =
+            serde_json::from_str(r#"[{"name": "Ada", "age": 36, "member": true, "note": null}]"#)
+                .unwrap();
+        let records = records_from_json(&value).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record["name"], "Ada");
+        assert_eq!(record["age"], "36");
+        assert_eq!(record["member"], "true");
+        assert_eq!(record["note"], "");
+    }
+
+    #[test]
+    fn rejects_nested_values() {
+        let value: Value = serde_json::from_str(r#"{"name": ["Ada"]}"#).unwrap();
+        assert!(records_from_json(&value).is_err());
+    }
+
+    #[test]
+    fn round_trips_a_record() {
+        let values = FieldValues::from([("name".to_owned(), "Zoë \"Z\"".to_owned())]);
+        let text = serde_json::to_string_pretty(&record_to_json(&values)).unwrap();
+        let value: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(records_from_json(&value).unwrap(), vec![values]);
+    }
+}
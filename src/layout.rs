@@ -0,0 +1,47 @@
+// layout.rs
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pdf_text_input::PdfInputFieldSerde;
+
+/// Field texts keyed by the `unique_id` of their field.
+pub type FieldValues = BTreeMap<String, String>;
+
+/// Snapshot of all input fields placed on a pdf document.
+///
+/// Field coordinates are pdf points measured from the top left corner of their page.
+/// Pages are rendered with one pixel per point, so these are the same values
+/// the picker works with on screen.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Layout {
+    pub pages: Vec<LayoutPage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LayoutPage {
+    pub width: f32,
+    pub height: f32,
+    pub fields: Vec<PdfInputFieldSerde>,
+}
+
+impl Layout {
+    pub fn fields(&self) -> impl Iterator<Item = &PdfInputFieldSerde> {
+        self.pages.iter().flat_map(|page| page.fields.iter())
+    }
+
+    /// Texts of all fields with a `unique_id`.
+    pub fn field_values(&self) -> FieldValues {
+        self.fields()
+            .filter(|field| !field.unique_id.is_empty())
+            .map(|field| (field.unique_id.clone(), field.text.clone()))
+            .collect()
+    }
+
+    pub fn fields_mut(&mut self) -> impl Iterator<Item = &mut PdfInputFieldSerde> {
+        self.pages
+            .iter_mut()
+            .flat_map(|page| page.fields.iter_mut())
+    }
+}
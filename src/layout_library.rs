@@ -0,0 +1,361 @@
+// layout_library.rs
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::SystemTime;
+
+use egui::TextureHandle;
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+
+use crate::app;
+use crate::fingerprint::Fingerprint;
+use crate::pdf_load;
+use crate::project::{self, PROJECT_FILE_EXTENSION, Project};
+
+/// Layouts scoring at least this much are suggested for a newly opened pdf.
+pub const MATCH_THRESHOLD: f32 = 0.6;
+
+/// Project file in the layout library.
+#[derive(Debug, Clone)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    pub project: Project,
+    pub modified: Option<SystemTime>,
+}
+
+impl LibraryEntry {
+    /// Form name of the layout, or its file name without extension.
+    pub fn name(&self) -> String {
+        if !self.project.form_name.is_empty() {
+            return self.project.form_name.clone();
+        }
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Reads all project files directly in `directory`, files which can not be read are skipped.
+pub fn scan_library(directory: &Path) -> Vec<LibraryEntry> {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::error!(
+                "Could not read layout library directory='{}': {e}",
+                directory.to_string_lossy()
+            );
+            return Vec::new();
+        }
+    };
+    let mut library: Vec<LibraryEntry> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == PROJECT_FILE_EXTENSION)
+        })
+        .filter_map(|path| match project::load_project(&path) {
+            Ok((project, _)) => Some(LibraryEntry {
+                modified: std::fs::metadata(&path).and_then(|m| m.modified()).ok(),
+                path,
+                project,
+            }),
+            Err(e) => {
+                log::warn!(
+                    "Skipping layout library file='{}': {e}",
+                    path.to_string_lossy()
+                );
+                None
+            }
+        })
+        .collect();
+    library.sort_by(|a, b| a.path.cmp(&b.path));
+    library
+}
+
+/// Library layout matching a newly opened pdf.
+#[derive(Debug, Clone)]
+pub struct LayoutMatch {
+    pub entry: LibraryEntry,
+    /// [`Fingerprint::match_score`], 1 for the identical file.
+    pub score: f32,
+}
+
+/// Layout in `directory` which matches `fingerprint` best, if any reaches [`MATCH_THRESHOLD`].
+pub fn best_match(directory: &Path, fingerprint: &Fingerprint) -> Option<LayoutMatch> {
+    scan_library(directory)
+        .into_iter()
+        .map(|entry| LayoutMatch {
+            score: entry.project.fingerprint.match_score(fingerprint),
+            entry,
+        })
+        .filter(|layout_match| layout_match.score >= MATCH_THRESHOLD)
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+/// Modification time as `YYYY-MM-DD HH:MM` in UTC.
+fn format_time(time: SystemTime) -> String {
+    let Ok(since_epoch) = time.duration_since(SystemTime::UNIX_EPOCH) else {
+        return String::new();
+    };
+    let seconds = since_epoch.as_secs();
+    let days = seconds / 86_400;
+    let minutes_of_day = seconds % 86_400 / 60;
+    // civil date from days since 1970-01-01, Howard Hinnant's algorithm
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+        minutes_of_day / 60,
+        minutes_of_day % 60
+    )
+}
+
+/// Width of the preview of the selected layout in points.
+const PREVIEW_WIDTH: f32 = 220.0;
+
+/// Side panel listing the layouts of the library folder.
+#[derive(Default)]
+pub struct LayoutLibraryPanel {
+    pub open: bool,
+    search: String,
+    /// Directory the entries were read from.
+    scanned_directory: Option<PathBuf>,
+    /// Entries of `scanned_directory` while they are read on another thread.
+    scan_receiver: Option<mpsc::Receiver<Vec<LibraryEntry>>>,
+    entries: Vec<LibraryEntry>,
+    selected: Option<PathBuf>,
+    /// First page of the selected layout's pdf, `None` if it could not be rendered.
+    preview: Option<(PathBuf, Option<TextureHandle>)>,
+    /// Preview of the entry at the path while it is rendered on another thread.
+    preview_receiver: Option<(PathBuf, mpsc::Receiver<Option<egui::ColorImage>>)>,
+}
+
+impl LayoutLibraryPanel {
+    /// Shows the panel, returns the layout the user chose to apply to the open pdf.
+    ///
+    /// `form_name` and `revision` belong to the current layout and are edited at the top.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        directory: Option<&Path>,
+        form_name: &mut String,
+        revision: &mut String,
+        pdf_open: bool,
+    ) -> Option<LibraryEntry> {
+        let mut apply = None;
+        egui::SidePanel::left("layout_library_panel")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.heading("Layout library");
+                egui::Grid::new("current_layout_form").show(ui, |ui| {
+                    ui.label("form name");
+                    ui.text_edit_singleline(form_name);
+                    ui.end_row();
+                    ui.label("revision");
+                    ui.text_edit_singleline(revision);
+                    ui.end_row();
+                });
+                ui.separator();
+                let Some(directory) = directory else {
+                    ui.label("Choose a layout library folder in the File menu.");
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.label("search: ");
+                    ui.text_edit_singleline(&mut self.search);
+                    if ui
+                        .button("⟳")
+                        .on_hover_text("Read the folder again")
+                        .clicked()
+                    {
+                        self.scanned_directory = None;
+                    }
+                });
+                if self.scanned_directory.as_deref() != Some(directory) {
+                    self.start_scan(directory.to_owned());
+                }
+                if self.poll_scan() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Reading the library folder…");
+                    });
+                    return;
+                }
+                self.ui_entries(ui);
+                let Some(entry) = self.selected_entry() else {
+                    return;
+                };
+                ui.separator();
+                let entry = entry.clone();
+                self.ui_preview(ui, &entry);
+                let apply_button = ui.add_enabled(pdf_open, egui::Button::new("Apply to open pdf"));
+                if apply_button.clicked() {
+                    apply = Some(entry);
+                }
+            });
+        apply
+    }
+
+    /// Reads the project files of `directory` on another thread.
+    fn start_scan(&mut self, directory: PathBuf) {
+        let (producer, receiver) = mpsc::channel();
+        let thread_directory = directory.clone();
+        std::thread::spawn(move || {
+            producer.send(scan_library(&thread_directory)).ok();
+        });
+        self.scan_receiver = Some(receiver);
+        self.scanned_directory = Some(directory);
+        self.entries.clear();
+    }
+
+    /// Takes the entries of the scanning thread, returns whether it is still running.
+    fn poll_scan(&mut self) -> bool {
+        let Some(receiver) = &self.scan_receiver else {
+            return false;
+        };
+        match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => {
+                log::error!("Connection to the layout library scan was lost.");
+            }
+            Ok(entries) => self.entries = entries,
+        }
+        self.scan_receiver = None;
+        false
+    }
+
+    /// Takes the preview of the rendering thread and loads it as texture once it is done.
+    fn poll_preview(&mut self, ctx: &egui::Context) {
+        let Some((path, receiver)) = &self.preview_receiver else {
+            return;
+        };
+        let image = match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => None,
+            Ok(image) => image,
+        };
+        let texture = image
+            .map(|image| ctx.load_texture("layout_library_preview", image, Default::default()));
+        self.preview = Some((path.clone(), texture));
+        self.preview_receiver = None;
+    }
+
+    fn selected_entry(&self) -> Option<&LibraryEntry> {
+        let selected = self.selected.as_ref()?;
+        self.entries.iter().find(|entry| &entry.path == selected)
+    }
+
+    fn ui_entries(&mut self, ui: &mut egui::Ui) {
+        let search = self.search.to_lowercase();
+        let matches_search = |entry: &LibraryEntry| {
+            search.is_empty()
+                || [
+                    entry.name(),
+                    entry.project.revision.clone(),
+                    entry.project.fingerprint.title.clone(),
+                ]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&search))
+        };
+        egui::ScrollArea::vertical()
+            .max_height(300.0)
+            .show(ui, |ui| {
+                egui::Grid::new("layout_library_entries")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("form");
+                        ui.strong("revision");
+                        ui.strong("pages");
+                        ui.strong("modified");
+                        ui.end_row();
+                        for entry in self.entries.iter().filter(|entry| matches_search(entry)) {
+                            let selected = self.selected.as_ref() == Some(&entry.path);
+                            if ui.selectable_label(selected, entry.name()).clicked() {
+                                self.selected = Some(entry.path.clone());
+                            }
+                            ui.label(&entry.project.revision);
+                            ui.label(entry.project.layout.pages.len().to_string());
+                            ui.label(entry.modified.map(format_time).unwrap_or_default());
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// First page of the entry's pdf with its fields, a blank page if the pdf is missing.
+    fn ui_preview(&mut self, ui: &mut egui::Ui, entry: &LibraryEntry) {
+        let Some(page) = entry.project.layout.pages.first() else {
+            ui.label("The layout has no pages.");
+            return;
+        };
+        let rendered = self.preview.as_ref().map(|(path, _)| path) == Some(&entry.path);
+        let rendering = self.preview_receiver.as_ref().map(|(path, _)| path) == Some(&entry.path);
+        if !rendered && !rendering {
+            let (producer, receiver) = mpsc::channel();
+            let pdf_path = entry.project.pdf_path.clone();
+            std::thread::spawn(move || {
+                producer.send(render_first_page(&pdf_path)).ok();
+            });
+            self.preview_receiver = Some((entry.path.clone(), receiver));
+        }
+        self.poll_preview(ui.ctx());
+        if self.preview_receiver.is_some() {
+            ui.spinner();
+        }
+        let scale = PREVIEW_WIDTH / page.width.max(1.0);
+        let (response, painter) = ui.allocate_painter(
+            egui::vec2(PREVIEW_WIDTH, page.height * scale),
+            egui::Sense::hover(),
+        );
+        let rect = response.rect;
+        match self
+            .preview
+            .as_ref()
+            .filter(|(path, _)| path == &entry.path)
+            .and_then(|(_, texture)| texture.as_ref())
+        {
+            Some(texture) => {
+                let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                painter.image(texture.id(), rect, uv, egui::Color32::WHITE);
+            }
+            None => {
+                painter.rect_filled(rect, 0.0, egui::Color32::WHITE);
+            }
+        }
+        for field in &page.fields {
+            let field_rect = egui::Rect::from_min_size(
+                rect.min + egui::vec2(field.pos_x, field.pos_y) * scale,
+                egui::vec2(field.width, field.height) * scale,
+            );
+            painter.rect_stroke(
+                field_rect,
+                0.0,
+                egui::Stroke::new(1.0, egui::Color32::RED),
+                egui::StrokeKind::Inside,
+            );
+        }
+    }
+}
+
+fn render_first_page(pdf_path: &Path) -> Option<egui::ColorImage> {
+    let pdfium = Pdfium::default();
+    let document = pdf_load::load_pdf_native(&pdfium, pdf_path).ok()?;
+    let page = document.pages().first().ok()?;
+    let config = PdfRenderConfig::new().set_target_width(PREVIEW_WIDTH as i32 * 2);
+    let bitmap = page.render_with_config(&config).ok()?;
+    Some(app::convert_to_color_image(bitmap.as_image()))
+}
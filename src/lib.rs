@@ -1,7 +1,45 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+mod acroform;
+mod anchor;
 mod app;
 pub use app::PdfCoordPickerApp;
+#[cfg(not(target_arch = "wasm32"))]
+mod batch_extraction;
+mod calibration;
+#[cfg(not(target_arch = "wasm32"))]
+mod compare;
+mod code_export;
+mod csv_table;
+mod exporter;
+mod extraction;
+#[cfg(not(target_arch = "wasm32"))]
+mod field_detection;
+mod field_naming;
 mod file_dialog;
+mod fingerprint;
+mod form_data;
+mod json;
+mod layout;
+#[cfg(not(target_arch = "wasm32"))]
+mod layout_library;
+#[cfg(not(target_arch = "wasm32"))]
+mod mail_merge;
+#[cfg(not(target_arch = "wasm32"))]
+mod onion_skin;
+mod page_inspector;
+mod pdf_export;
 mod pdf_load;
+mod pdf_objects;
 mod pdf_text_input;
+mod pdftk_dump;
+mod project;
+#[cfg(not(target_arch = "wasm32"))]
+mod recovery;
+#[cfg(not(target_arch = "wasm32"))]
+pub use recovery::APP_ID;
+#[cfg(not(target_arch = "wasm32"))]
+mod revision_migration;
+mod snapping;
+mod text_layer;
+mod units;
@@ -0,0 +1,408 @@
+// mail_merge.rs
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+
+use pdfium_render::prelude::{Pdfium, PdfiumError};
+
+use crate::anchor::{self, AnchorWarning};
+use crate::calibration::CalibrationProfile;
+use crate::csv_table::CsvTable;
+use crate::layout::Layout;
+use crate::{pdf_export, pdf_load};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailMergeOutput {
+    FilePerRow,
+    Combined,
+}
+
+#[derive(Debug)]
+pub enum MailMergeError {
+    Cancelled,
+    PdfError(PdfiumError),
+}
+
+impl fmt::Display for MailMergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "No destination was selected."),
+            Self::PdfError(e) => write!(f, "Pdf error: {e}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowIssueKind {
+    MissingValue,
+    Overlong,
+}
+
+/// Problem with a single value of the csv file, `row` starts at 1 for the first data row.
+#[derive(Debug, Clone)]
+pub struct RowIssue {
+    pub row: usize,
+    pub unique_id: String,
+    pub kind: RowIssueKind,
+}
+
+impl fmt::Display for RowIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            RowIssueKind::MissingValue => {
+                write!(
+                    f,
+                    "row {}: no value for field '{}'",
+                    self.row, self.unique_id
+                )
+            }
+            RowIssueKind::Overlong => write!(
+                f,
+                "row {}: value of field '{}' is wider than the field",
+                self.row, self.unique_id
+            ),
+        }
+    }
+}
+
+pub struct MailMergeReport {
+    pub files_written: usize,
+    /// Anchored fields which could not be placed unambiguously on the source pdf.
+    pub anchor_warnings: Vec<AnchorWarning>,
+    pub issues: Vec<RowIssue>,
+}
+
+/// Everything a mail merge run needs, owned so it can be moved to a worker thread.
+pub struct MailMergeJob {
+    pub source_path: PathBuf,
+    pub layout: Layout,
+    pub table: CsvTable,
+    pub output: MailMergeOutput,
+    pub file_name_template: String,
+    pub destination: PathBuf,
+    pub calibration: Option<CalibrationProfile>,
+}
+
+/// Copy of `layout` with the text of every field that has a matching column set to the row value.
+///
+/// Fields without a matching column keep the text entered in the picker.
+pub fn layout_for_row(layout: &Layout, table: &CsvTable, row: &[String]) -> Layout {
+    let mut layout = layout.clone();
+    for field in layout.fields_mut() {
+        if let Some(value) = table.value(row, &field.unique_id) {
+            value.clone_into(&mut field.text);
+        }
+    }
+    layout
+}
+
+/// Replaces `{column}` placeholders in `template` with the values of `record`
+/// and `{row}` with the row number.
+pub fn file_name_for_row(
+    template: &str,
+    table: &CsvTable,
+    row_number: usize,
+    record: &[String],
+) -> String {
+    let mut file_name = template.replace("{row}", &row_number.to_string());
+    for header in &table.headers {
+        let value = table.value(record, header).unwrap_or_default();
+        file_name = file_name.replace(&format!("{{{header}}}"), value);
+    }
+    let file_name: String = file_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    let file_name = file_name.trim();
+    if file_name.is_empty() {
+        format!("row_{row_number}")
+    } else {
+        file_name.to_owned()
+    }
+}
+
+pub fn run_mail_merge(job: &MailMergeJob) -> Result<MailMergeReport, MailMergeError> {
+    let pdfium = Pdfium::default();
+    let mut source_layout = job.layout.clone();
+    let anchor_warnings = pdf_load::load_pdf_native(&pdfium, &job.source_path)
+        .and_then(|document| anchor::resolve_document_anchors(&mut source_layout, &document))
+        .map_err(MailMergeError::PdfError)?;
+    let mut combined = match job.output {
+        MailMergeOutput::Combined => {
+            Some(pdfium.create_new_pdf().map_err(MailMergeError::PdfError)?)
+        }
+        MailMergeOutput::FilePerRow => None,
+    };
+    let mut used_file_names = Vec::with_capacity(job.table.rows.len());
+    let mut issues = Vec::new();
+    let mut files_written = 0;
+
+    for (row_index, row) in job.table.rows.iter().enumerate() {
+        let row_number = row_index + 1;
+        let layout = layout_for_row(&source_layout, &job.table, row);
+        issues.extend(
+            layout
+                .fields()
+                .filter(|field| !field.unique_id.is_empty() && field.text.is_empty())
+                .map(|field| RowIssue {
+                    row: row_number,
+                    unique_id: field.unique_id.clone(),
+                    kind: RowIssueKind::MissingValue,
+                }),
+        );
+
+        let (document, overflowing_fields) = pdf_export::fill_pdf_document(
+            &pdfium,
+            &job.source_path,
+            &layout,
+            job.calibration.as_ref(),
+        )
+        .map_err(MailMergeError::PdfError)?;
+        issues.extend(overflowing_fields.into_iter().map(|unique_id| RowIssue {
+            row: row_number,
+            unique_id,
+            kind: RowIssueKind::Overlong,
+        }));
+
+        if let Some(combined) = &mut combined {
+            combined
+                .pages_mut()
+                .append(&document)
+                .map_err(MailMergeError::PdfError)?;
+        } else {
+            let mut file_name =
+                file_name_for_row(&job.file_name_template, &job.table, row_number, row);
+            if used_file_names.contains(&file_name) {
+                file_name = format!("{file_name}_{row_number}");
+            }
+            let path = job.destination.join(format!("{file_name}.pdf"));
+            document
+                .save_to_file(&path)
+                .map_err(MailMergeError::PdfError)?;
+            used_file_names.push(file_name);
+            files_written += 1;
+        }
+    }
+
+    if let Some(combined) = combined {
+        combined
+            .save_to_file(&job.destination)
+            .map_err(MailMergeError::PdfError)?;
+        files_written = 1;
+    }
+    Ok(MailMergeReport {
+        files_written,
+        anchor_warnings,
+        issues,
+    })
+}
+
+type MailMergeResult = Result<MailMergeReport, MailMergeError>;
+
+/// Window for configuring and running a mail merge of the current layout with a csv file.
+pub struct MailMergeWindow {
+    pub open: bool,
+    csv_path: Option<PathBuf>,
+    table: Option<Result<CsvTable, String>>,
+    output: MailMergeOutput,
+    file_name_template: String,
+    receiver: Option<mpsc::Receiver<MailMergeResult>>,
+    result: Option<MailMergeResult>,
+}
+
+impl Default for MailMergeWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            csv_path: None,
+            table: None,
+            output: MailMergeOutput::FilePerRow,
+            file_name_template: "{row}".to_owned(),
+            receiver: None,
+            result: None,
+        }
+    }
+}
+
+impl MailMergeWindow {
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        source_path: Option<&Path>,
+        layout: &Layout,
+        calibration: Option<&CalibrationProfile>,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Mail merge")
+            .open(&mut open)
+            .default_width(400.)
+            .show(ctx, |ui| {
+                self.ui_csv_file(ui, layout);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.output,
+                        MailMergeOutput::FilePerRow,
+                        "one pdf per row",
+                    );
+                    ui.radio_value(
+                        &mut self.output,
+                        MailMergeOutput::Combined,
+                        "one combined pdf",
+                    );
+                });
+                if self.output == MailMergeOutput::FilePerRow {
+                    ui.horizontal(|ui| {
+                        ui.label("file name: ");
+                        ui.text_edit_singleline(&mut self.file_name_template);
+                    })
+                    .response
+                    .on_hover_text(
+                        "{column} is replaced by the value of the column, {row} by the row number.",
+                    );
+                }
+                ui.separator();
+                self.ui_run(ui, source_path, layout, calibration);
+            });
+        self.open = open;
+    }
+
+    fn ui_csv_file(&mut self, ui: &mut egui::Ui, layout: &Layout) {
+        ui.horizontal(|ui| {
+            if ui.button("Open csv file…").clicked()
+                && let Some(path) = rfd::FileDialog::new()
+                    .add_filter("csv", &["csv", "txt"])
+                    .pick_file()
+            {
+                self.table = Some(
+                    std::fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|text| CsvTable::parse(&text).map_err(|e| e.to_string())),
+                );
+                self.csv_path = Some(path);
+            }
+            if let Some(path) = &self.csv_path {
+                ui.label(path.to_string_lossy());
+            }
+        });
+        match &self.table {
+            Some(Ok(table)) => {
+                ui.label(format!(
+                    "{} rows, {} columns",
+                    table.rows.len(),
+                    table.headers.len()
+                ));
+                let unmatched: Vec<&str> = layout
+                    .fields()
+                    .filter(|field| {
+                        !field.unique_id.is_empty()
+                            && table.column_index(&field.unique_id).is_none()
+                    })
+                    .map(|field| field.unique_id.as_str())
+                    .collect();
+                if !unmatched.is_empty() {
+                    ui.label(format!("Fields without column: {}", unmatched.join(", ")));
+                }
+            }
+            Some(Err(e)) => {
+                ui.label(format!("Could not read csv file: {e}"));
+            }
+            None => {}
+        }
+    }
+
+    fn ui_run(
+        &mut self,
+        ui: &mut egui::Ui,
+        source_path: Option<&Path>,
+        layout: &Layout,
+        calibration: Option<&CalibrationProfile>,
+    ) {
+        if let Some(receiver) = &self.receiver {
+            match receiver.try_recv() {
+                Err(TryRecvError::Empty) => {
+                    ui.spinner();
+                    return;
+                }
+                Err(TryRecvError::Disconnected) => {
+                    self.receiver = None;
+                    ui.label("Error: Connection to mail merge was lost.");
+                }
+                Ok(result) => {
+                    self.receiver = None;
+                    self.result = Some(result);
+                }
+            }
+        }
+
+        let table = match (&self.table, source_path) {
+            (Some(Ok(table)), Some(source_path)) => Some((table, source_path)),
+            _ => None,
+        };
+        if ui
+            .add_enabled(table.is_some(), egui::Button::new("Run mail merge…"))
+            .clicked()
+            && let Some((table, source_path)) = table
+        {
+            self.result = None;
+            let job = MailMergeJob {
+                source_path: source_path.to_owned(),
+                layout: layout.clone(),
+                table: table.clone(),
+                output: self.output,
+                file_name_template: self.file_name_template.clone(),
+                destination: PathBuf::new(),
+                calibration: calibration.cloned(),
+            };
+            self.receiver = Some(spawn_mail_merge_thread(job));
+        }
+
+        match &self.result {
+            Some(Ok(report)) => {
+                ui.label(format!(
+                    "{} file(s) written, {} issue(s).",
+                    report.files_written,
+                    report.anchor_warnings.len() + report.issues.len()
+                ));
+                egui::ScrollArea::vertical()
+                    .max_height(200.)
+                    .show(ui, |ui| {
+                        for warning in &report.anchor_warnings {
+                            ui.label(warning.to_string());
+                        }
+                        for issue in &report.issues {
+                            ui.label(issue.to_string());
+                        }
+                    });
+            }
+            Some(Err(e)) => {
+                ui.label(e.to_string());
+            }
+            None => {}
+        }
+    }
+}
+
+fn spawn_mail_merge_thread(mut job: MailMergeJob) -> mpsc::Receiver<MailMergeResult> {
+    let (producer, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let destination = match job.output {
+            MailMergeOutput::FilePerRow => rfd::FileDialog::new().pick_folder(),
+            MailMergeOutput::Combined => rfd::FileDialog::new()
+                .set_file_name("mail_merge.pdf")
+                .save_file(),
+        };
+        let result = if let Some(destination) = destination {
+            job.destination = destination;
+            run_mail_merge(&job)
+        } else {
+            Err(MailMergeError::Cancelled)
+        };
+        producer.send(result).ok();
+    });
+    receiver
+}
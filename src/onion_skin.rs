@@ -0,0 +1,192 @@
+// onion_skin.rs
+
+use std::path::{Path, PathBuf};
+
+use egui::{Color32, Rect, TextureHandle, Vec2, pos2};
+use image::DynamicImage;
+use pdfium_render::prelude::Pdfium;
+
+use crate::app;
+use crate::pdf_load;
+
+/// Reference document shown semi transparent over the pages, e.g. a scan of a filled form.
+pub struct OnionSkin {
+    pub open: bool,
+    path: Option<PathBuf>,
+    /// One texture per page of a reference pdf, a single one for an image.
+    pages: Vec<TextureHandle>,
+    error: Option<String>,
+    visible: bool,
+    opacity: f32,
+    /// Shift of the reference in points.
+    offset: Vec2,
+    /// Reference points per page point, images are first scaled to the page width.
+    scale: f32,
+    /// Page an image reference is shown on, pdf pages cover the page with the same index.
+    image_page: usize,
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self {
+            open: false,
+            path: None,
+            pages: Vec::new(),
+            error: None,
+            visible: true,
+            opacity: 0.5,
+            offset: Vec2::ZERO,
+            scale: 1.0,
+            image_page: 0,
+        }
+    }
+}
+
+fn load_reference(path: &Path) -> Result<Vec<DynamicImage>, String> {
+    let is_pdf = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+    if is_pdf {
+        let pdfium = Pdfium::default();
+        pdf_load::load_pdf_native(&pdfium, path)
+            .and_then(app::create_images_from_pdf)
+            .map_err(|e| e.to_string())
+    } else {
+        image::open(path)
+            .map(|image| vec![image])
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl OnionSkin {
+    pub fn load(&mut self, ctx: &egui::Context, path: PathBuf) {
+        match load_reference(&path) {
+            Ok(images) => {
+                self.pages = images
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, image)| {
+                        ctx.load_texture(
+                            format!("onion_skin_{index}"),
+                            app::convert_to_color_image(image),
+                            Default::default(),
+                        )
+                    })
+                    .collect();
+                self.error = None;
+                self.visible = true;
+            }
+            Err(e) => {
+                self.pages.clear();
+                self.error = Some(format!(
+                    "Could not load file='{}': {e}",
+                    path.to_string_lossy()
+                ));
+            }
+        }
+        self.path = Some(path);
+        self.open = true;
+    }
+
+    fn is_image(&self) -> bool {
+        self.path.as_deref().is_some_and(|path| {
+            !path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+        })
+    }
+
+    /// Paints the reference for page `page` over `page_rect`, the page's rect on screen.
+    pub fn paint(&self, painter: &egui::Painter, page_rect: Rect, page: usize) {
+        if !self.visible {
+            return;
+        }
+        let texture = if self.is_image() {
+            self.pages.first().filter(|_| page == self.image_page)
+        } else {
+            self.pages.get(page)
+        };
+        let Some(texture) = texture else {
+            return;
+        };
+        let mut size = texture.size_vec2();
+        if self.is_image() {
+            // scans have their own resolution, start out as wide as the page
+            size *= page_rect.width() / size.x.max(1.0);
+        }
+        let rect = Rect::from_min_size(page_rect.min + self.offset, size * self.scale);
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        let tint = Color32::from_white_alpha((self.opacity * 255.0) as u8);
+        painter
+            .with_clip_rect(page_rect)
+            .image(texture.id(), rect, uv, tint);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Onion skin")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if ui.button("Load reference pdf or image…").clicked()
+                    && let Some(path) = rfd::FileDialog::new()
+                        .add_filter("pdf or image", &["pdf", "png", "jpg", "jpeg"])
+                        .pick_file()
+                {
+                    self.load(ctx, path);
+                }
+                if let Some(path) = &self.path {
+                    ui.label(path.to_string_lossy());
+                }
+                if let Some(e) = &self.error {
+                    ui.label(e.as_str());
+                }
+                if self.pages.is_empty() {
+                    return;
+                }
+                ui.checkbox(&mut self.visible, "visible");
+                egui::Grid::new("onion_skin_settings").show(ui, |ui| {
+                    ui.label("opacity");
+                    ui.add(egui::Slider::new(&mut self.opacity, 0.0..=1.0));
+                    ui.end_row();
+                    ui.label("offset x");
+                    ui.add(
+                        egui::DragValue::new(&mut self.offset.x)
+                            .speed(0.5)
+                            .suffix(" pt"),
+                    );
+                    ui.end_row();
+                    ui.label("offset y");
+                    ui.add(
+                        egui::DragValue::new(&mut self.offset.y)
+                            .speed(0.5)
+                            .suffix(" pt"),
+                    );
+                    ui.end_row();
+                    ui.label("scale");
+                    ui.add(
+                        egui::DragValue::new(&mut self.scale)
+                            .speed(0.001)
+                            .range(0.1..=10.0),
+                    );
+                    ui.end_row();
+                    if self.is_image() {
+                        ui.label("page");
+                        ui.add(
+                            egui::DragValue::new(&mut self.image_page)
+                                .custom_formatter(|page, _| (page as usize + 1).to_string())
+                                .custom_parser(|text| {
+                                    text.trim().parse::<f64>().ok().map(|page| page - 1.0)
+                                }),
+                        );
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Reset position").clicked() {
+                    self.offset = Vec2::ZERO;
+                    self.scale = 1.0;
+                }
+            });
+        self.open = open;
+    }
+}
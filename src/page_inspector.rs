@@ -0,0 +1,182 @@
+// page_inspector.rs
+
+use std::fmt;
+
+use egui::{Pos2, Rect, pos2};
+use pdfium_render::prelude::{
+    PdfDocument, PdfMatrix, PdfPage, PdfPageAnnotationCommon as _, PdfPageObject,
+    PdfPageObjectCommon as _, PdfPageObjectsCommon as _, PdfiumError,
+};
+
+use crate::units::CoordinateUnit;
+
+/// Thin objects like lines are hit this many points beside their bounds.
+const HIT_MARGIN: f32 = 2.0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectKind {
+    Text,
+    Path,
+    Image,
+    Shading,
+    /// Form `XObject`, content drawn from a reusable stream.
+    Form,
+    /// Annotation with its subtype, form field widgets among them.
+    Annotation(String),
+    Unsupported,
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text run"),
+            Self::Path => write!(f, "path"),
+            Self::Image => write!(f, "image"),
+            Self::Shading => write!(f, "shading"),
+            Self::Form => write!(f, "form xobject"),
+            Self::Annotation(subtype) => write!(f, "annotation ({subtype})"),
+            Self::Unsupported => write!(f, "unsupported object"),
+        }
+    }
+}
+
+/// Page object or annotation as shown by the inspector.
+#[derive(Debug, Clone)]
+pub struct PageObjectInfo {
+    pub kind: ObjectKind,
+    /// In points from the top left corner of the page.
+    pub bounds: Rect,
+    pub text: Option<String>,
+    pub font: Option<String>,
+    /// Font size in points after scaling by the text matrix.
+    pub font_size: Option<f32>,
+}
+
+/// Objects and annotations of `page`, objects inside forms after the form itself.
+pub fn page_objects(page: &PdfPage<'_>) -> Result<Vec<PageObjectInfo>, PdfiumError> {
+    let page_height = page.height().value;
+    let mut objects = Vec::new();
+    for object in page.objects().iter() {
+        add_object(&object, PdfMatrix::IDENTITY, page_height, &mut objects)?;
+    }
+    for annotation in page.annotations().iter() {
+        let bounds = annotation.bounds()?;
+        objects.push(PageObjectInfo {
+            kind: ObjectKind::Annotation(format!("{:?}", annotation.annotation_type())),
+            bounds: Rect::from_min_max(
+                pos2(bounds.left().value, page_height - bounds.top().value),
+                pos2(bounds.right().value, page_height - bounds.bottom().value),
+            ),
+            text: None,
+            font: None,
+            font_size: None,
+        });
+    }
+    Ok(objects)
+}
+
+/// Adds `object`, whose bounds are transformed by `parent` into page space.
+fn add_object(
+    object: &PdfPageObject<'_>,
+    parent: PdfMatrix,
+    page_height: f32,
+    objects: &mut Vec<PageObjectInfo>,
+) -> Result<(), PdfiumError> {
+    let bounds = object.bounds()?;
+    let corners = [
+        (bounds.left(), bounds.top()),
+        (bounds.right(), bounds.bottom()),
+    ]
+    .map(|(x, y)| {
+        let (x, y) = parent.apply_to_points(x, y);
+        pos2(x.value, page_height - y.value)
+    });
+    let mut info = PageObjectInfo {
+        kind: ObjectKind::Unsupported,
+        bounds: Rect::from_two_pos(corners[0], corners[1]),
+        text: None,
+        font: None,
+        font_size: None,
+    };
+    match object {
+        PdfPageObject::Text(text) => {
+            info.kind = ObjectKind::Text;
+            info.text = Some(text.text());
+            info.font = Some(text.font().name());
+            info.font_size = Some(text.scaled_font_size().value);
+        }
+        PdfPageObject::Path(_) => info.kind = ObjectKind::Path,
+        PdfPageObject::Image(_) => info.kind = ObjectKind::Image,
+        PdfPageObject::Shading(_) => info.kind = ObjectKind::Shading,
+        PdfPageObject::XObjectForm(form) => {
+            info.kind = ObjectKind::Form;
+            objects.push(info);
+            let matrix = form.matrix()?.multiply(parent);
+            for index in form.as_range() {
+                add_object(&form.get(index)?, matrix, page_height, objects)?;
+            }
+            return Ok(());
+        }
+        PdfPageObject::Unsupported(_) => {}
+    }
+    objects.push(info);
+    Ok(())
+}
+
+/// Objects of every page of `document`, indexed like the pages.
+pub fn document_objects(
+    document: &PdfDocument<'_>,
+) -> Result<Vec<Vec<PageObjectInfo>>, PdfiumError> {
+    document
+        .pages()
+        .iter()
+        .map(|page| page_objects(&page))
+        .collect()
+}
+
+/// Smallest object at `point`, in points from the top left corner of the page.
+///
+/// Objects covering large areas like page backgrounds only count where nothing else is.
+pub fn object_at(objects: &[PageObjectInfo], point: Pos2) -> Option<&PageObjectInfo> {
+    objects
+        .iter()
+        .filter(|object| object.bounds.expand(HIT_MARGIN).contains(point))
+        .min_by(|a, b| a.bounds.area().total_cmp(&b.bounds.area()))
+}
+
+/// Type, bounds and font of `object` on a page `page_height` points high.
+pub fn inspector_ui(ui: &mut egui::Ui, object: &PageObjectInfo, page_height: f32) {
+    ui.strong(object.kind.to_string());
+    egui::Grid::new("inspected_object_grid").show(ui, |ui| {
+        ui.label("");
+        for unit in [CoordinateUnit::Points, CoordinateUnit::Millimeters] {
+            ui.strong(unit.label());
+        }
+        ui.end_row();
+        let bounds = object.bounds;
+        let values = [
+            ("left", bounds.left()),
+            ("top", bounds.top()),
+            ("width", bounds.width()),
+            ("height", bounds.height()),
+            // the pdf convention measures from the bottom left corner
+            ("pdf bottom", page_height - bounds.bottom()),
+        ];
+        for (name, pt) in values {
+            ui.label(name);
+            for unit in [CoordinateUnit::Points, CoordinateUnit::Millimeters] {
+                ui.label(format!("{:.2}", unit.convert_points(pt)));
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(font) = &object.font {
+        ui.label(format!("font: {font}"));
+    }
+    if let Some(font_size) = object.font_size {
+        ui.label(format!("font size: {font_size:.2} pt"));
+    }
+    if let Some(text) = &object.text {
+        ui.label(format!("text: '{text}'"));
+    }
+}
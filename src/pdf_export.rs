@@ -0,0 +1,105 @@
+// pdf_export.rs
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use pdfium_render::prelude::{
+    PdfDocument, PdfFontToken, PdfPage, PdfPageObjectCommon as _, PdfPageObjectsCommon as _,
+    PdfPagePaperSize, PdfPoints, Pdfium, PdfiumError,
+};
+
+use crate::calibration::CalibrationProfile;
+use crate::layout::{Layout, LayoutPage};
+
+/// Largest font size used for field texts; smaller fields shrink the text to fit their height.
+const MAX_FONT_SIZE: f32 = 11.0;
+
+/// Creates blank pages of the same size as the source document containing only the field texts,
+/// meant to be printed onto pre-printed forms.
+pub fn create_overlay_pdf(
+    pdfium: &Pdfium,
+    layout: &Layout,
+    calibration: Option<&CalibrationProfile>,
+) -> Result<Vec<u8>, PdfiumError> {
+    let mut document = pdfium.create_new_pdf()?;
+    let font = document.fonts_mut().helvetica();
+    for layout_page in &layout.pages {
+        let mut page = document
+            .pages_mut()
+            .create_page_at_end(PdfPagePaperSize::new_custom(
+                PdfPoints::new(layout_page.width),
+                PdfPoints::new(layout_page.height),
+            ))?;
+        draw_field_texts(&mut page, layout_page, font, calibration)?;
+    }
+    document.save_to_bytes()
+}
+
+/// Creates a copy of the source document with the field texts drawn onto its pages.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn create_filled_pdf(
+    pdfium: &Pdfium,
+    source_path: &Path,
+    layout: &Layout,
+    calibration: Option<&CalibrationProfile>,
+) -> Result<Vec<u8>, PdfiumError> {
+    let (document, _) = fill_pdf_document(pdfium, source_path, layout, calibration)?;
+    document.save_to_bytes()
+}
+
+/// Loads the source document and draws the field texts onto its pages.
+///
+/// Also returns the ids of all fields whose text is wider than the field itself.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn fill_pdf_document<'a>(
+    pdfium: &'a Pdfium,
+    source_path: &Path,
+    layout: &Layout,
+    calibration: Option<&CalibrationProfile>,
+) -> Result<(PdfDocument<'a>, Vec<String>), PdfiumError> {
+    let mut document = pdfium.load_pdf_from_file(source_path, None)?;
+    let font = document.fonts_mut().helvetica();
+    let mut overflowing_fields = Vec::new();
+    for (page_index, layout_page) in layout.pages.iter().enumerate() {
+        let mut page = document.pages().get(page_index as u16)?;
+        overflowing_fields.extend(draw_field_texts(&mut page, layout_page, font, calibration)?);
+    }
+    Ok((document, overflowing_fields))
+}
+
+fn draw_field_texts(
+    page: &mut PdfPage<'_>,
+    layout_page: &LayoutPage,
+    font: PdfFontToken,
+    calibration: Option<&CalibrationProfile>,
+) -> Result<Vec<String>, PdfiumError> {
+    let mut overflowing_fields = Vec::new();
+    let (offset_x, offset_y) = calibration
+        .map(CalibrationProfile::correction_pt)
+        .unwrap_or((0., 0.));
+    for field in layout_page
+        .fields
+        .iter()
+        .filter(|field| !field.text.is_empty())
+    {
+        let font_size = field_font_size(field.height);
+        // pdf coordinates start at the bottom left, the baseline is centered vertically
+        let field_bottom = layout_page.height - field.pos_y - field.height;
+        let baseline = field_bottom + (field.height - font_size) / 2.;
+        let text_object = page.objects_mut().create_text_object(
+            PdfPoints::new(field.pos_x + offset_x),
+            PdfPoints::new(baseline + offset_y),
+            &field.text,
+            font,
+            PdfPoints::new(font_size),
+        )?;
+        if text_object.width()?.value > field.width {
+            overflowing_fields.push(field.unique_id.clone());
+        }
+    }
+    Ok(overflowing_fields)
+}
+
+fn field_font_size(field_height: f32) -> f32 {
+    (field_height * 0.7).clamp(1., MAX_FONT_SIZE)
+}
@@ -0,0 +1,858 @@
+// pdf_objects.rs
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Minimal model of the pdf object syntax, enough to edit the dictionaries of an existing file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PdfObject {
+    Null,
+    Bool(bool),
+    /// Numbers keep their original text.
+    Number(String),
+    Name(String),
+    String(Vec<u8>),
+    Array(Vec<PdfObject>),
+    Dictionary(PdfDictionary),
+    Reference(ObjectId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ObjectId {
+    pub number: u32,
+    pub generation: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PdfDictionary(Vec<(String, PdfObject)>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PdfSyntaxErrorKind {
+    /// The file does not follow the pdf syntax.
+    Malformed,
+    /// The objects are indexed by a cross-reference stream, which [`PdfUpdate`] cannot read.
+    CrossReferenceStream,
+}
+
+#[derive(Debug)]
+pub struct PdfSyntaxError {
+    pub position: usize,
+    pub kind: PdfSyntaxErrorKind,
+    pub message: &'static str,
+}
+
+impl PdfSyntaxError {
+    pub fn malformed(position: usize, message: &'static str) -> Self {
+        Self {
+            position,
+            kind: PdfSyntaxErrorKind::Malformed,
+            message,
+        }
+    }
+
+    /// Whether the file uses a cross-reference stream, which [`PdfUpdate`] cannot read.
+    pub fn is_cross_reference_stream(&self) -> bool {
+        self.kind == PdfSyntaxErrorKind::CrossReferenceStream
+    }
+}
+
+impl fmt::Display for PdfSyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.position)
+    }
+}
+
+impl PdfObject {
+    pub fn name(name: &str) -> Self {
+        Self::Name(name.to_owned())
+    }
+
+    pub fn integer(value: i64) -> Self {
+        Self::Number(value.to_string())
+    }
+
+    /// Real number with at most two decimals, which is precise enough for positions in points.
+    pub fn real(value: f32) -> Self {
+        let text = format!("{value:.2}");
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        Self::Number(if text == "-0" { "0" } else { text }.to_owned())
+    }
+
+    /// Text string, encoded as UTF-16 if it is not plain ascii.
+    pub fn text(text: &str) -> Self {
+        if text.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+            Self::String(text.as_bytes().to_vec())
+        } else {
+            let mut bytes = vec![0xfe, 0xff];
+            bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+            Self::String(bytes)
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write_to(&mut out);
+        out
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Null => out.extend_from_slice(b"null"),
+            Self::Bool(value) => out.extend_from_slice(if *value { b"true" } else { b"false" }),
+            Self::Number(value) => out.extend_from_slice(value.as_bytes()),
+            Self::Name(name) => {
+                out.push(b'/');
+                for &byte in name.as_bytes() {
+                    if (b'!'..=b'~').contains(&byte) && !is_delimiter(byte) && byte != b'#' {
+                        out.push(byte);
+                    } else {
+                        out.extend_from_slice(format!("#{byte:02X}").as_bytes());
+                    }
+                }
+            }
+            Self::String(bytes) => {
+                out.push(b'(');
+                for &byte in bytes {
+                    match byte {
+                        b'(' | b')' | b'\\' => out.extend_from_slice(&[b'\\', byte]),
+                        b' '..=b'~' => out.push(byte),
+                        _ => out.extend_from_slice(format!("\\{byte:03o}").as_bytes()),
+                    }
+                }
+                out.push(b')');
+            }
+            Self::Array(values) => {
+                out.push(b'[');
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        out.push(b' ');
+                    }
+                    value.write_to(out);
+                }
+                out.push(b']');
+            }
+            Self::Dictionary(dictionary) => dictionary.write_to(out),
+            Self::Reference(id) => {
+                out.extend_from_slice(format!("{} {} R", id.number, id.generation).as_bytes());
+            }
+        }
+    }
+}
+
+impl PdfDictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder style [`Self::insert`].
+    pub fn with(mut self, key: &str, value: PdfObject) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&PdfObject> {
+        self.0
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut PdfObject> {
+        self.0
+            .iter_mut()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets `key` to `value`, replacing a previous value in place.
+    pub fn insert(&mut self, key: &str, value: PdfObject) {
+        match self.get_mut(key) {
+            Some(entry) => *entry = value,
+            None => self.0.push((key.to_owned(), value)),
+        }
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(b"<<");
+        for (key, value) in &self.0 {
+            PdfObject::Name(key.clone()).write_to(out);
+            out.push(b' ');
+            value.write_to(out);
+        }
+        out.extend_from_slice(b">>");
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b'\0' | b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+struct PdfParser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl PdfParser<'_> {
+    fn error<T>(&self, message: &'static str) -> Result<T, PdfSyntaxError> {
+        Err(PdfSyntaxError::malformed(self.position, message))
+    }
+
+    fn cross_reference_stream_error<T>(&self) -> Result<T, PdfSyntaxError> {
+        Err(PdfSyntaxError {
+            position: self.position,
+            kind: PdfSyntaxErrorKind::CrossReferenceStream,
+            message: "cross-reference streams are not supported",
+        })
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if byte == b'%' {
+                while self
+                    .next()
+                    .is_some_and(|byte| byte != b'\n' && byte != b'\r')
+                {}
+            } else if is_whitespace(byte) {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Regular characters up to the next whitespace or delimiter.
+    fn token(&mut self) -> &[u8] {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|byte| !is_whitespace(byte) && !is_delimiter(byte))
+        {
+            self.position += 1;
+        }
+        self.bytes.get(start..self.position).unwrap_or_default()
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), PdfSyntaxError> {
+        self.skip_whitespace();
+        if self.token() == keyword.as_bytes() {
+            Ok(())
+        } else {
+            self.error("unexpected keyword")
+        }
+    }
+
+    fn unsigned(&mut self) -> Result<u64, PdfSyntaxError> {
+        self.skip_whitespace();
+        let token = self.token();
+        match std::str::from_utf8(token).ok().and_then(|t| t.parse().ok()) {
+            Some(value) => Ok(value),
+            None => self.error("expected an unsigned integer"),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<PdfObject, PdfSyntaxError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'/') => {
+                self.position += 1;
+                self.parse_name().map(PdfObject::Name)
+            }
+            Some(b'(') => self.parse_literal_string().map(PdfObject::String),
+            Some(b'<') if self.bytes.get(self.position + 1) == Some(&b'<') => {
+                self.parse_dictionary().map(PdfObject::Dictionary)
+            }
+            Some(b'<') => self.parse_hex_string().map(PdfObject::String),
+            Some(b'[') => self.parse_array(),
+            Some(byte) if byte.is_ascii_digit() || matches!(byte, b'+' | b'-' | b'.') => {
+                Ok(self.parse_number_or_reference())
+            }
+            Some(_) => match self.token() {
+                b"true" => Ok(PdfObject::Bool(true)),
+                b"false" => Ok(PdfObject::Bool(false)),
+                b"null" => Ok(PdfObject::Null),
+                _ => self.error("unexpected token"),
+            },
+            None => self.error("unexpected end of file"),
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, PdfSyntaxError> {
+        let mut name = Vec::new();
+        let mut token = self.token().iter().copied();
+        while let Some(byte) = token.next() {
+            if byte == b'#' {
+                let hex: Vec<u8> = token.by_ref().take(2).collect();
+                match std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(byte) => name.push(byte),
+                    None => return self.error("invalid escape in name"),
+                }
+            } else {
+                name.push(byte);
+            }
+        }
+        Ok(String::from_utf8_lossy(&name).into_owned())
+    }
+
+    fn parse_number_or_reference(&mut self) -> PdfObject {
+        let number = String::from_utf8_lossy(self.token()).into_owned();
+        let after_number = self.position;
+        // "12 0 R" is a reference, otherwise the following integer is a separate object
+        if let Ok(object_number) = number.parse()
+            && let Ok(generation) = self.unsigned()
+            && let Ok(generation) = u16::try_from(generation)
+            && self.keyword("R").is_ok()
+        {
+            return PdfObject::Reference(ObjectId {
+                number: object_number,
+                generation,
+            });
+        }
+        self.position = after_number;
+        PdfObject::Number(number)
+    }
+
+    fn parse_literal_string(&mut self) -> Result<Vec<u8>, PdfSyntaxError> {
+        self.position += 1;
+        let mut bytes = Vec::new();
+        let mut depth = 0;
+        loop {
+            match self.next() {
+                Some(b'(') => {
+                    depth += 1;
+                    bytes.push(b'(');
+                }
+                Some(b')') if depth == 0 => return Ok(bytes),
+                Some(b')') => {
+                    depth -= 1;
+                    bytes.push(b')');
+                }
+                Some(b'\\') => match self.next() {
+                    Some(b'n') => bytes.push(b'\n'),
+                    Some(b'r') => bytes.push(b'\r'),
+                    Some(b't') => bytes.push(b'\t'),
+                    Some(b'b') => bytes.push(0x08),
+                    Some(b'f') => bytes.push(0x0c),
+                    Some(b'\r') => {
+                        self.position += usize::from(self.peek() == Some(b'\n'));
+                    }
+                    Some(b'\n') => {}
+                    Some(digit @ b'0'..=b'7') => {
+                        let mut value = u32::from(digit - b'0');
+                        for _ in 0..2 {
+                            match self.peek() {
+                                Some(digit @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(digit - b'0');
+                                    self.position += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        bytes.push((value & 0xff) as u8);
+                    }
+                    Some(byte) => bytes.push(byte),
+                    None => return self.error("unterminated string"),
+                },
+                Some(byte) => bytes.push(byte),
+                None => return self.error("unterminated string"),
+            }
+        }
+    }
+
+    fn parse_hex_string(&mut self) -> Result<Vec<u8>, PdfSyntaxError> {
+        self.position += 1;
+        let mut digits = Vec::new();
+        loop {
+            match self.next() {
+                Some(b'>') => break,
+                Some(byte) if byte.is_ascii_hexdigit() => digits.push(byte),
+                Some(byte) if is_whitespace(byte) => {}
+                _ => return self.error("invalid hex string"),
+            }
+        }
+        if digits.len() % 2 == 1 {
+            // a missing last digit is zero
+            digits.push(b'0');
+        }
+        Ok(digits
+            .chunks(2)
+            .filter_map(|pair| {
+                let pair = std::str::from_utf8(pair).ok()?;
+                u8::from_str_radix(pair, 16).ok()
+            })
+            .collect())
+    }
+
+    fn parse_array(&mut self) -> Result<PdfObject, PdfSyntaxError> {
+        self.position += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b']') {
+                self.position += 1;
+                return Ok(PdfObject::Array(values));
+            }
+            values.push(self.parse_object()?);
+        }
+    }
+
+    fn parse_dictionary(&mut self) -> Result<PdfDictionary, PdfSyntaxError> {
+        self.position += 2;
+        let mut dictionary = PdfDictionary::new();
+        loop {
+            self.skip_whitespace();
+            match self.next() {
+                Some(b'>') if self.next() == Some(b'>') => return Ok(dictionary),
+                Some(b'/') => {
+                    let key = self.parse_name()?;
+                    let value = self.parse_object()?;
+                    dictionary.insert(&key, value);
+                }
+                _ => return self.error("expected a name as dictionary key"),
+            }
+        }
+    }
+}
+
+enum ChangedObject {
+    Object(PdfObject),
+    Stream(PdfDictionary, Vec<u8>),
+}
+
+/// Existing pdf file with objects added or replaced by appending an incremental update.
+///
+/// Only files with classic cross-reference tables are supported, files with cross-reference
+/// streams fail with an error for which [`PdfSyntaxError::is_cross_reference_stream`] holds.
+pub struct PdfUpdate<'a> {
+    bytes: &'a [u8],
+    offsets: BTreeMap<u32, (usize, u16)>,
+    trailer: PdfDictionary,
+    startxref: usize,
+    next_number: u32,
+    changed: BTreeMap<ObjectId, ChangedObject>,
+}
+
+impl<'a> PdfUpdate<'a> {
+    /// Reads the cross-reference tables of `bytes`, newest first.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, PdfSyntaxError> {
+        let tail_start = bytes.len().saturating_sub(1024);
+        let tail = bytes.get(tail_start..).unwrap_or_default();
+        let Some(startxref_position) = tail
+            .windows(b"startxref".len())
+            .rposition(|window| window == b"startxref")
+        else {
+            return Err(PdfSyntaxError::malformed(
+                bytes.len(),
+                "startxref not found",
+            ));
+        };
+        let mut parser = PdfParser {
+            bytes,
+            position: tail_start + startxref_position + b"startxref".len(),
+        };
+        let startxref = parser.unsigned()? as usize;
+
+        let mut offsets = BTreeMap::new();
+        let mut trailer = None;
+        let mut visited = BTreeSet::new();
+        let mut section = Some(startxref);
+        while let Some(position) = section.filter(|&position| visited.insert(position)) {
+            parser.position = position;
+            if parser.keyword("xref").is_err() {
+                return parser.cross_reference_stream_error();
+            }
+            let section_trailer = loop {
+                parser.skip_whitespace();
+                if parser
+                    .bytes
+                    .get(parser.position..)
+                    .unwrap_or_default()
+                    .starts_with(b"trailer")
+                {
+                    parser.keyword("trailer")?;
+                    match parser.parse_object()? {
+                        PdfObject::Dictionary(dictionary) => break dictionary,
+                        _ => return parser.error("trailer is not a dictionary"),
+                    }
+                }
+                let first = parser.unsigned()?;
+                let count = parser.unsigned()?;
+                for number in first..first + count {
+                    let offset = parser.unsigned()? as usize;
+                    let generation = parser.unsigned()?;
+                    parser.skip_whitespace();
+                    let in_use = parser.token() == b"n";
+                    if in_use && let Ok(number) = u32::try_from(number) {
+                        offsets.entry(number).or_insert((offset, generation as u16));
+                    }
+                }
+            };
+            if section_trailer.get("XRefStm").is_some() {
+                // hybrid file, objects in object streams are missing from the table
+                return parser.cross_reference_stream_error();
+            }
+            section = match section_trailer.get("Prev") {
+                Some(PdfObject::Number(prev)) => prev.parse().ok(),
+                _ => None,
+            };
+            trailer.get_or_insert(section_trailer);
+        }
+
+        let trailer = trailer.unwrap_or_default();
+        let size = match trailer.get("Size") {
+            Some(PdfObject::Number(size)) => size.parse().unwrap_or(0),
+            _ => 0,
+        };
+        let next_number = offsets
+            .keys()
+            .next_back()
+            .map_or(1, |number| number + 1)
+            .max(size);
+        Ok(Self {
+            bytes,
+            offsets,
+            trailer,
+            startxref,
+            next_number,
+            changed: BTreeMap::new(),
+        })
+    }
+
+    pub fn trailer(&self) -> &PdfDictionary {
+        &self.trailer
+    }
+
+    pub fn object(&self, id: ObjectId) -> Result<PdfObject, PdfSyntaxError> {
+        match self.changed.get(&id) {
+            Some(ChangedObject::Object(object)) => return Ok(object.clone()),
+            Some(ChangedObject::Stream(dictionary, _)) => {
+                return Ok(PdfObject::Dictionary(dictionary.clone()));
+            }
+            None => {}
+        }
+        let Some(&(offset, _)) = self.offsets.get(&id.number) else {
+            // references to missing objects are null
+            return Ok(PdfObject::Null);
+        };
+        let mut parser = PdfParser {
+            bytes: self.bytes,
+            position: offset,
+        };
+        if parser.unsigned()? != u64::from(id.number) {
+            return parser.error("cross-reference table points to the wrong object");
+        }
+        parser.unsigned()?;
+        parser.keyword("obj")?;
+        parser.parse_object()
+    }
+
+    /// The referenced object if `object` is a reference, otherwise `object` itself.
+    pub fn resolve(&self, object: &PdfObject) -> Result<PdfObject, PdfSyntaxError> {
+        match object {
+            PdfObject::Reference(id) => self.object(*id),
+            object => Ok(object.clone()),
+        }
+    }
+
+    /// Dictionary of the referenced or direct `object`.
+    pub fn resolve_dictionary(&self, object: &PdfObject) -> Result<PdfDictionary, PdfSyntaxError> {
+        match self.resolve(object)? {
+            PdfObject::Dictionary(dictionary) => Ok(dictionary),
+            _ => Err(PdfSyntaxError::malformed(0, "expected a dictionary")),
+        }
+    }
+
+    /// Ids of the page objects in document order.
+    pub fn page_ids(&self) -> Result<Vec<ObjectId>, PdfSyntaxError> {
+        let catalog =
+            self.resolve_dictionary(self.trailer.get("Root").unwrap_or(&PdfObject::Null))?;
+        let mut page_ids = Vec::new();
+        let mut visited = BTreeSet::new();
+        // depth first, children are pushed in reverse to keep the page order
+        let mut stack: Vec<PdfObject> = catalog.get("Pages").into_iter().cloned().collect();
+        while let Some(node) = stack.pop() {
+            let PdfObject::Reference(id) = node else {
+                continue;
+            };
+            if !visited.insert(id) {
+                continue;
+            }
+            let node = self.resolve_dictionary(&node)?;
+            match node.get("Kids") {
+                Some(kids) => {
+                    if let PdfObject::Array(kids) = self.resolve(kids)? {
+                        stack.extend(kids.into_iter().rev());
+                    }
+                }
+                None => page_ids.push(id),
+            }
+        }
+        Ok(page_ids)
+    }
+
+    pub fn add(&mut self, object: PdfObject) -> ObjectId {
+        let id = self.next_id();
+        self.changed.insert(id, ChangedObject::Object(object));
+        id
+    }
+
+    pub fn add_stream(&mut self, dictionary: PdfDictionary, data: Vec<u8>) -> ObjectId {
+        let id = self.next_id();
+        self.changed
+            .insert(id, ChangedObject::Stream(dictionary, data));
+        id
+    }
+
+    pub fn replace(&mut self, id: ObjectId, object: PdfObject) {
+        self.changed.insert(id, ChangedObject::Object(object));
+    }
+
+    fn next_id(&mut self) -> ObjectId {
+        let number = self.next_number;
+        self.next_number += 1;
+        ObjectId {
+            number,
+            generation: 0,
+        }
+    }
+
+    /// The original file followed by the changed objects, a cross-reference table and trailer.
+    pub fn write(self) -> Vec<u8> {
+        let mut out = self.bytes.to_vec();
+        if !out.ends_with(b"\n") {
+            out.push(b'\n');
+        }
+        let mut entries = Vec::with_capacity(self.changed.len());
+        for (id, object) in self.changed {
+            entries.push((id, out.len()));
+            out.extend_from_slice(format!("{} {} obj\n", id.number, id.generation).as_bytes());
+            match object {
+                ChangedObject::Object(object) => object.write_to(&mut out),
+                ChangedObject::Stream(dictionary, data) => {
+                    dictionary
+                        .with("Length", PdfObject::integer(data.len() as i64))
+                        .write_to(&mut out);
+                    out.extend_from_slice(b"\nstream\n");
+                    out.extend_from_slice(&data);
+                    out.extend_from_slice(b"\nendstream");
+                }
+            }
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref_position = out.len();
+        out.extend_from_slice(b"xref\n");
+        let mut remaining = entries.as_slice();
+        while let Some(((first, _), _)) = remaining.split_first() {
+            // subsections of consecutive object numbers
+            let count = remaining
+                .iter()
+                .enumerate()
+                .take_while(|(index, (id, _))| id.number as usize == first.number as usize + index)
+                .count();
+            let (subsection, rest) = remaining.split_at(count);
+            out.extend_from_slice(format!("{} {count}\n", first.number).as_bytes());
+            for (id, offset) in subsection {
+                out.extend_from_slice(
+                    format!("{offset:010} {:05} n\r\n", id.generation).as_bytes(),
+                );
+            }
+            remaining = rest;
+        }
+
+        let mut trailer = PdfDictionary::new()
+            .with("Size", PdfObject::integer(i64::from(self.next_number)))
+            .with("Prev", PdfObject::integer(self.startxref as i64));
+        for key in ["Root", "Info", "ID"] {
+            if let Some(value) = self.trailer.get(key) {
+                trailer.insert(key, value.clone());
+            }
+        }
+        out.extend_from_slice(b"trailer\n");
+        trailer.write_to(&mut out);
+        out.extend_from_slice(format!("\nstartxref\n{xref_position}\n%%EOF\n").as_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// One page of 200 by 100 points with a classic cross-reference table.
+    pub(crate) fn minimal_pdf() -> Vec<u8> {
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 200 100] >>",
+        ];
+        let mut pdf = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, object) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", index + 1).as_bytes());
+        }
+        let xref = pdf.len();
+        let size = objects.len() + 1;
+        pdf.extend_from_slice(format!("xref\n0 {size}\n0000000000 65535 f\r\n").as_bytes());
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{offset:010} 00000 n\r\n").as_bytes());
+        }
+        pdf.extend_from_slice(
+            format!("trailer\n<< /Size {size} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n")
+                .as_bytes(),
+        );
+        pdf
+    }
+
+    fn parse(bytes: &[u8]) -> PdfObject {
+        PdfParser { bytes, position: 0 }.parse_object().unwrap()
+    }
+
+    fn reference(number: u32, generation: u16) -> PdfObject {
+        PdfObject::Reference(ObjectId { number, generation })
+    }
+
+    #[test]
+    fn dictionary_round_trip() {
+        let object = parse(
+            b"<< /Type /Annot /Rect [0 -1.5 +3 .5] /Name /A#20B /Parent 12 0 R \
+              /Kids [1 2 3 R 4] /Null null /On true /Nested <</K (v)>> >>",
+        );
+        let PdfObject::Dictionary(dictionary) = &object else {
+            panic!("expected a dictionary, got {object:?}");
+        };
+        assert_eq!(dictionary.get("Type"), Some(&PdfObject::name("Annot")));
+        assert_eq!(dictionary.get("Name"), Some(&PdfObject::name("A B")));
+        assert_eq!(dictionary.get("Parent"), Some(&reference(12, 0)));
+        assert_eq!(
+            dictionary.get("Kids"),
+            Some(&PdfObject::Array(vec![
+                PdfObject::integer(1),
+                reference(2, 3),
+                PdfObject::integer(4),
+            ]))
+        );
+        assert_eq!(dictionary.get("On"), Some(&PdfObject::Bool(true)));
+        assert_eq!(parse(&object.to_bytes()), object);
+    }
+
+    #[test]
+    fn literal_string_escapes() {
+        let object = parse(b"(a\\(b\\) (nested) \\\\ \\101\\7\\n\\\nend)");
+        assert_eq!(
+            object,
+            PdfObject::String(b"a(b) (nested) \\ A\x07\nend".to_vec())
+        );
+        assert_eq!(parse(&object.to_bytes()), object);
+    }
+
+    #[test]
+    fn hex_string() {
+        assert_eq!(
+            parse(b"<48 65 6C6c 6>"),
+            PdfObject::String(b"Hell`".to_vec())
+        );
+        assert!(
+            PdfParser {
+                bytes: b"<4G>",
+                position: 0,
+            }
+            .parse_object()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn text_and_real() {
+        assert_eq!(PdfObject::text("Name"), PdfObject::String(b"Name".to_vec()));
+        assert_eq!(
+            PdfObject::text("Straße"),
+            PdfObject::String(b"\xfe\xff\0S\0t\0r\0a\0\xdf\0e".to_vec())
+        );
+        assert_eq!(PdfObject::real(1.5).to_bytes(), b"1.5");
+        assert_eq!(PdfObject::real(-0.001).to_bytes(), b"0");
+        assert_eq!(PdfObject::real(12.0).to_bytes(), b"12");
+    }
+
+    #[test]
+    fn written_update_reopens() {
+        let pdf = minimal_pdf();
+        let mut update = PdfUpdate::new(&pdf).unwrap();
+        let page_ids = update.page_ids().unwrap();
+        assert_eq!(page_ids.iter().map(|id| id.number).collect::<Vec<_>>(), [3]);
+        let added = update.add(PdfObject::Dictionary(
+            PdfDictionary::new().with("T", PdfObject::text("name")),
+        ));
+        let stream = update.add_stream(PdfDictionary::new(), b"q Q".to_vec());
+        let mut page = update.resolve_dictionary(&reference(3, 0)).unwrap();
+        page.insert(
+            "Annots",
+            PdfObject::Array(vec![PdfObject::Reference(added)]),
+        );
+        update.replace(page_ids[0], PdfObject::Dictionary(page.clone()));
+        let written = update.write();
+        assert!(written.starts_with(&pdf));
+
+        let reopened = PdfUpdate::new(&written).unwrap();
+        assert_eq!(reopened.page_ids().unwrap(), page_ids);
+        assert_eq!(
+            reopened.object(page_ids[0]).unwrap(),
+            PdfObject::Dictionary(page)
+        );
+        assert_eq!(
+            reopened
+                .resolve_dictionary(&PdfObject::Reference(added))
+                .unwrap(),
+            PdfDictionary::new().with("T", PdfObject::text("name"))
+        );
+        assert_eq!(
+            reopened.object(stream).unwrap(),
+            PdfObject::Dictionary(PdfDictionary::new().with("Length", PdfObject::integer(3)))
+        );
+        assert_eq!(reopened.trailer().get("Size"), Some(&PdfObject::integer(6)));
+        assert_eq!(reopened.trailer().get("Root"), Some(&reference(1, 0)));
+    }
+
+    #[test]
+    fn cross_reference_stream_is_reported() {
+        let pdf = b"%PDF-1.5\n1 0 obj\n<< /Type /XRef /Size 2 /W [1 2 1] >>\nstream\n\
+                    endstream\nendobj\nstartxref\n9\n%%EOF\n";
+        let error = PdfUpdate::new(pdf).err().unwrap();
+        assert!(error.is_cross_reference_stream());
+
+        let hybrid = String::from_utf8(minimal_pdf())
+            .unwrap()
+            .replace("/Root 1 0 R", "/Root 1 0 R /XRefStm 9");
+        let error = PdfUpdate::new(hybrid.as_bytes()).err().unwrap();
+        assert!(error.is_cross_reference_stream());
+    }
+
+    #[test]
+    fn other_errors_are_malformed() {
+        let error = PdfUpdate::new(b"%PDF-1.4\nno cross-reference table")
+            .err()
+            .unwrap();
+        assert_eq!(error.kind, PdfSyntaxErrorKind::Malformed);
+        assert!(!error.is_cross_reference_stream());
+    }
+}
@@ -3,15 +3,44 @@ use egui::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::anchor::{self, FieldAnchor};
+use crate::snapping::SnapGuides;
+
 //pub struct PdfTableInput {}
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum PdfInputFieldKind {
+    #[default]
+    Text,
+    /// Checked unless the text is empty, "0", "false", "no" or "off".
+    Checkbox,
+    /// Text with one character per equally wide cell.
+    Comb { cells: u32 },
+}
+
+impl PdfInputFieldKind {
+    pub fn is_checked(text: &str) -> bool {
+        let text = text.trim();
+        let unchecked = ["", "0", "false", "no", "off"];
+        !unchecked.iter().any(|value| text.eq_ignore_ascii_case(value))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct PdfInputFieldSerde {
-    unique_id: String,
-    pos_x: f32,
-    pos_y: f32,
-    width: f32,
-    height: f32,
+    pub unique_id: String,
+    pub pos_x: f32,
+    pub pos_y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub kind: PdfInputFieldKind,
+    #[serde(default)]
+    pub anchor: Option<FieldAnchor>,
 }
 
 enum CursorAction{
@@ -27,6 +56,10 @@ pub struct PdfInputFieldState {
     cursor_action: CursorAction,
     pub rect: Rect,
     pub text: String,
+    pub required: bool,
+    pub kind: PdfInputFieldKind,
+    /// Anchor text and the top left corner of its occurrence the field is placed relative to.
+    pub anchor: Option<(String, Pos2)>,
 }
 
 impl PdfInputFieldState {
@@ -36,6 +69,48 @@ impl PdfInputFieldState {
             cursor_action: CursorAction::None,
             rect: rect,
             text: String::new(),
+            required: false,
+            kind: PdfInputFieldKind::Text,
+            anchor: None,
+        }
+    }
+
+    pub fn from_serde(field: &PdfInputFieldSerde) -> Self {
+        Self {
+            unique_id: field.unique_id.clone(),
+            cursor_action: CursorAction::None,
+            rect: Rect::from_min_size(
+                egui::pos2(field.pos_x, field.pos_y),
+                egui::vec2(field.width, field.height),
+            ),
+            text: field.text.clone(),
+            required: field.required,
+            kind: field.kind,
+            anchor: field.anchor.as_ref().map(|field_anchor| {
+                let origin = anchor::anchor_origin(
+                    egui::pos2(field.pos_x, field.pos_y),
+                    field_anchor,
+                );
+                (field_anchor.text.clone(), origin)
+            }),
+        }
+    }
+
+    pub fn to_serde(&self) -> PdfInputFieldSerde {
+        PdfInputFieldSerde {
+            unique_id: self.unique_id.clone(),
+            pos_x: self.rect.left(),
+            pos_y: self.rect.top(),
+            width: self.rect.width(),
+            height: self.rect.height(),
+            text: self.text.clone(),
+            required: self.required,
+            kind: self.kind,
+            anchor: self.anchor.as_ref().map(|(text, origin)| FieldAnchor {
+                text: text.clone(),
+                offset_x: self.rect.left() - origin.x,
+                offset_y: self.rect.top() - origin.y,
+            }),
         }
     }
 }
@@ -56,13 +131,54 @@ impl PdfInputField {
         state: &mut PdfInputFieldState,
         page_resp: &Response,
         painter: &mut Painter,
+        snap_guides: Option<&SnapGuides>,
         ui: &mut egui::Ui,
     ) -> Response {
         let input_resp = self.ui_draw_input_field(state, page_resp, painter, ui);
         Self::ui_resize_control(state, &page_resp, &input_resp);
+        if let Some(snap_guides) = snap_guides {
+            Self::snap_resized_edge(state, page_resp, painter, snap_guides);
+        }
         input_resp
     }
 
+    /// Moves the edge being resized onto a nearby line or text baseline of the page and
+    /// highlights that line.
+    fn snap_resized_edge(
+        state: &mut PdfInputFieldState,
+        page_resp: &Response,
+        painter: &Painter,
+        snap_guides: &SnapGuides,
+    ) {
+        let rect = state.rect;
+        let line = match state.cursor_action {
+            CursorAction::None => None,
+            CursorAction::ResizeNorth => snap_guides
+                .snap_y(rect.top(), rect.x_range())
+                .filter(|line| line.from.y < rect.bottom())
+                .inspect(|line| *state.rect.top_mut() = line.from.y),
+            CursorAction::ResizeSouth => snap_guides
+                .snap_y(rect.bottom(), rect.x_range())
+                .filter(|line| line.from.y > rect.top())
+                .inspect(|line| *state.rect.bottom_mut() = line.from.y),
+            CursorAction::ResizeWest => snap_guides
+                .snap_x(rect.left(), rect.y_range())
+                .filter(|line| line.from.x < rect.right())
+                .inspect(|line| *state.rect.left_mut() = line.from.x),
+            CursorAction::ResizeEast => snap_guides
+                .snap_x(rect.right(), rect.y_range())
+                .filter(|line| line.from.x > rect.left())
+                .inspect(|line| *state.rect.right_mut() = line.from.x),
+        };
+        if let Some(line) = line {
+            let offset = page_resp.rect.min.to_vec2();
+            painter.line_segment(
+                [line.from + offset, line.to + offset],
+                Stroke::new(1.5, Color32::from_rgb(0, 150, 255)),
+            );
+        }
+    }
+
     fn ui_draw_input_field(
         &self,
         state: &mut PdfInputFieldState,
@@ -0,0 +1,245 @@
+// pdftk_dump.rs
+
+use std::fmt;
+
+use crate::acroform::{FLAG_COMB, FLAG_PUSH_BUTTON, FLAG_REQUIRED};
+use crate::layout::Layout;
+use crate::pdf_text_input::{PdfInputFieldKind, PdfInputFieldSerde};
+
+#[derive(Debug)]
+pub struct PdftkDumpError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for PdftkDumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in line {}", self.message, self.line)
+    }
+}
+
+/// One field record of `pdftk dump_data_fields`.
+#[derive(Debug, Clone, Default)]
+pub struct DumpedField {
+    pub field_type: String,
+    pub name: String,
+    pub value: String,
+    pub flags: i64,
+    pub max_length: Option<u32>,
+    /// Page number starting at 1, records without one are on the first page.
+    pub page: Option<usize>,
+    /// Left, bottom, right and top in pdf points from the bottom left corner of the page.
+    pub rect: Option<[f32; 4]>,
+}
+
+/// Parses the records of `pdftk dump_data_fields`, which are separated by `---` lines.
+pub fn parse_dump_data_fields(text: &str) -> Result<Vec<DumpedField>, PdftkDumpError> {
+    let mut fields = Vec::new();
+    let mut field: Option<DumpedField> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message| PdftkDumpError {
+            line: line_number,
+            message,
+        };
+        let line = line.trim_end_matches('\r');
+        if line.trim() == "---" {
+            fields.extend(field.take());
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        let record = field.get_or_insert_with(DumpedField::default);
+        match key {
+            "FieldType" => value.clone_into(&mut record.field_type),
+            "FieldName" => value.clone_into(&mut record.name),
+            "FieldValue" => value.clone_into(&mut record.value),
+            "FieldFlags" => {
+                record.flags = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .ok_or_else(|| error("invalid FieldFlags"))?;
+            }
+            "FieldMaxLength" => {
+                record.max_length = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .ok()
+                        .ok_or_else(|| error("invalid FieldMaxLength"))?,
+                );
+            }
+            "FieldPage" | "FieldPageNumber" => {
+                record.page = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .ok()
+                        .ok_or_else(|| error("invalid page"))?,
+                );
+            }
+            "FieldRect" => record.rect = Some(parse_rect(value).ok_or(error("invalid FieldRect"))?),
+            _ => {}
+        }
+    }
+    fields.extend(field);
+    Ok(fields)
+}
+
+/// Four numbers, optionally in brackets and separated by commas.
+fn parse_rect(value: &str) -> Option<[f32; 4]> {
+    let numbers: Vec<f32> = value
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '[' | ']'))
+        .filter(|number| !number.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    let [x1, y1, x2, y2] = numbers.as_slice() else {
+        return None;
+    };
+    Some([x1.min(*x2), y1.min(*y2), x1.max(*x2), y1.max(*y2)])
+}
+
+/// Adds the dumped fields to the pages of `layout`, converting their rects to the picker's
+/// top left origin with the height of their page.
+///
+/// Fields without `FieldRect`, on missing pages, push buttons and signatures are skipped,
+/// returns the number of skipped fields.
+pub fn add_dumped_fields(layout: &mut Layout, fields: &[DumpedField]) -> usize {
+    let mut skipped = 0;
+    for field in fields {
+        let kind = match field.field_type.as_str() {
+            "Text" => match field.max_length {
+                Some(cells) if field.flags & FLAG_COMB != 0 => PdfInputFieldKind::Comb { cells },
+                _ => PdfInputFieldKind::Text,
+            },
+            "Button" if field.flags & FLAG_PUSH_BUTTON == 0 => PdfInputFieldKind::Checkbox,
+            "Choice" => PdfInputFieldKind::Text,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let page_index = field.page.unwrap_or(1).saturating_sub(1);
+        let (Some([left, bottom, right, top]), Some(page)) =
+            (field.rect, layout.pages.get_mut(page_index))
+        else {
+            skipped += 1;
+            continue;
+        };
+        page.fields.push(PdfInputFieldSerde {
+            unique_id: field.name.clone(),
+            pos_x: left,
+            pos_y: page.height - top,
+            width: right - left,
+            height: top - bottom,
+            text: field.value.clone(),
+            required: field.flags & FLAG_REQUIRED != 0,
+            kind,
+            anchor: None,
+        });
+    }
+    skipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutPage;
+
+    const DUMP: &str = "---\n\
+        FieldType: Text\n\
+        FieldName: name\n\
+        FieldFlags: 2\n\
+        FieldValue: Ada\n\
+        FieldJustification: Left\n\
+        FieldRect: [50 700 250 720]\n\
+        ---\n\
+        FieldType: Text\n\
+        FieldName: zip\n\
+        FieldFlags: 16777216\n\
+        FieldMaxLength: 5\n\
+        FieldPage: 2\n\
+        FieldRect: 300,120,100,100\n\
+        ---\n\
+        FieldType: Button\n\
+        FieldName: submit\n\
+        FieldFlags: 65536\n\
+        FieldRect: [10 10 60 30]\n\
+        ---\n\
+        FieldType: Button\n\
+        FieldName: member\n\
+        FieldFlags: 0\n\
+        FieldRect: [10 10 22 22]\n\
+        ---\n\
+        FieldType: Signature\n\
+        FieldName: signature\n\
+        FieldRect: [10 40 200 80]\n";
+
+    fn layout() -> Layout {
+        let page = LayoutPage {
+            width: 595.,
+            height: 842.,
+            fields: Vec::new(),
+        };
+        Layout {
+            pages: vec![page.clone(), page],
+        }
+    }
+
+    #[test]
+    fn parses_records() {
+        let fields = parse_dump_data_fields(DUMP).unwrap();
+        assert_eq!(fields.len(), 5);
+        assert_eq!(fields[0].name, "name");
+        assert_eq!(fields[0].value, "Ada");
+        assert_eq!(fields[0].rect, Some([50., 700., 250., 720.]));
+        // corners in any order and separated by commas
+        assert_eq!(fields[1].rect, Some([100., 100., 300., 120.]));
+        assert_eq!(fields[1].page, Some(2));
+        assert_eq!(fields[1].max_length, Some(5));
+    }
+
+    #[test]
+    fn converts_to_top_left_origin() {
+        let fields = parse_dump_data_fields(DUMP).unwrap();
+        let mut layout = layout();
+        let skipped = add_dumped_fields(&mut layout, &fields);
+        // the push button and the signature
+        assert_eq!(skipped, 2);
+
+        let first_page = &layout.pages[0].fields;
+        assert_eq!(first_page.len(), 2);
+        let name = &first_page[0];
+        assert_eq!((name.pos_x, name.pos_y), (50., 842. - 720.));
+        assert_eq!((name.width, name.height), (200., 20.));
+        assert!(name.required);
+        assert_eq!(name.kind, PdfInputFieldKind::Text);
+        assert_eq!(first_page[1].unique_id, "member");
+        assert_eq!(first_page[1].kind, PdfInputFieldKind::Checkbox);
+
+        let zip = &layout.pages[1].fields[0];
+        assert_eq!((zip.pos_x, zip.pos_y), (100., 842. - 120.));
+        assert_eq!(zip.kind, PdfInputFieldKind::Comb { cells: 5 });
+        assert!(!zip.required);
+    }
+
+    #[test]
+    fn max_length_without_comb_flag_is_text() {
+        let dump = "FieldType: Text\nFieldName: note\nFieldMaxLength: 5\nFieldRect: 0 0 50 10\n";
+        let mut layout = layout();
+        add_dumped_fields(&mut layout, &parse_dump_data_fields(dump).unwrap());
+        assert_eq!(layout.pages[0].fields[0].kind, PdfInputFieldKind::Text);
+    }
+
+    #[test]
+    fn reports_the_line_of_invalid_values() {
+        let dump = "---\nFieldType: Text\nFieldName: a\nFieldFlags: many\n";
+        let error = parse_dump_data_fields(dump).unwrap_err();
+        assert_eq!(error.line, 4);
+        assert_eq!(error.message, "invalid FieldFlags");
+    }
+}
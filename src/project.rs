@@ -0,0 +1,265 @@
+// project.rs
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::calibration::CalibrationProfile;
+use crate::fingerprint::Fingerprint;
+use crate::layout::Layout;
+use crate::units::{CoordinateOrigin, CoordinateUnit};
+
+pub const PROJECT_FILE_EXTENSION: &str = "pcp";
+
+/// Upgrade of a project file to the next format version.
+struct Migration {
+    /// What changed, shown to the user when a file is upgraded.
+    description: &'static str,
+    upgrade: fn(&mut Project),
+}
+
+/// The migration at index `i` upgrades format version `i + 1` to `i + 2`.
+///
+/// Fields added in a version get their default when older files are read, a migration only
+/// has to convert values whose meaning changed.
+const MIGRATIONS: [Migration; 4] = [
+    Migration {
+        description: "the file records its format version",
+        upgrade: |_| {},
+    },
+    Migration {
+        description: "the file records a fingerprint of its pdf to find matching layouts",
+        upgrade: |project| {
+            project.fingerprint.content_hash = project.pdf_hash.clone();
+            project.fingerprint.page_sizes = project
+                .layout
+                .pages
+                .iter()
+                .map(|page| [page.width, page.height])
+                .collect();
+        },
+    },
+    Migration {
+        description: "the file records the name and revision of its form",
+        upgrade: |_| {},
+    },
+    Migration {
+        description: "fields can be positioned relative to a text anchor",
+        upgrade: |_| {},
+    },
+];
+
+/// Format version written by this build of the app.
+pub const PROJECT_FORMAT_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Files from before the format had a version.
+fn unversioned() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+struct VersionHeader {
+    #[serde(default = "unversioned")]
+    version: u32,
+}
+
+/// Everything needed to continue working on a layout: the pdf it belongs to, its fields
+/// and the settings the coordinates were taken with.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Project {
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    pub pdf_path: PathBuf,
+    /// Name of the form type for the layout library, the file name if empty.
+    pub form_name: String,
+    /// Edition of the form, e.g. `2024-01`.
+    pub revision: String,
+    /// [`content_hash`] of the pdf file when the project was saved.
+    pub pdf_hash: String,
+    pub fingerprint: Fingerprint,
+    pub layout: Layout,
+    pub unit: CoordinateUnit,
+    pub origin: CoordinateOrigin,
+    pub calibration: Option<CalibrationProfile>,
+    pub page_max_width: String,
+    pub page_max_height: String,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            version: PROJECT_FORMAT_VERSION,
+            pdf_path: PathBuf::new(),
+            form_name: String::new(),
+            revision: String::new(),
+            pdf_hash: String::new(),
+            fingerprint: Fingerprint::default(),
+            layout: Layout::default(),
+            unit: CoordinateUnit::default(),
+            origin: CoordinateOrigin::default(),
+            calibration: None,
+            page_max_width: String::new(),
+            page_max_height: String::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProjectError {
+    Io(std::io::Error),
+    Format(String),
+    /// Written by a newer version of the app with a format this one does not know.
+    NewerVersion {
+        version: u32,
+    },
+}
+
+impl fmt::Display for ProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Format(e) => write!(f, "invalid project file: {e}"),
+            Self::NewerVersion { version } => write!(
+                f,
+                "the project file has format version {version}, this version of the app reads \
+                 up to version {PROJECT_FORMAT_VERSION}. Please update the app to open it"
+            ),
+        }
+    }
+}
+
+/// 64 bit FNV-1a hash of `bytes` as hex, to notice when a pdf file changed.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+pub fn save_project(path: &Path, project: &Project) -> Result<(), ProjectError> {
+    let text = ron::ser::to_string_pretty(project, ron::ser::PrettyConfig::default())
+        .map_err(|e| ProjectError::Format(e.to_string()))?;
+    std::fs::write(path, text).map_err(ProjectError::Io)
+}
+
+/// Reads a project file and upgrades it to the current format version.
+///
+/// Returns the project and a line for every upgrade that was applied.
+pub fn load_project(path: &Path) -> Result<(Project, Vec<String>), ProjectError> {
+    let text = std::fs::read_to_string(path).map_err(ProjectError::Io)?;
+    // a newer format may not parse as a project at all, so its version is checked first
+    let header: VersionHeader =
+        ron::from_str(&text).map_err(|e| ProjectError::Format(e.to_string()))?;
+    if header.version > PROJECT_FORMAT_VERSION {
+        return Err(ProjectError::NewerVersion {
+            version: header.version,
+        });
+    }
+    let mut project = ron::from_str(&text).map_err(|e| ProjectError::Format(e.to_string()))?;
+    let report = migrate(&mut project)?;
+    Ok((project, report))
+}
+
+/// Upgrades `project` step by step to [`PROJECT_FORMAT_VERSION`], returns a line per step.
+pub fn migrate(project: &mut Project) -> Result<Vec<String>, ProjectError> {
+    if project.version > PROJECT_FORMAT_VERSION {
+        return Err(ProjectError::NewerVersion {
+            version: project.version,
+        });
+    }
+    let mut report = Vec::new();
+    let first = project.version.saturating_sub(1) as usize;
+    for migration in MIGRATIONS.iter().skip(first) {
+        (migration.upgrade)(project);
+        report.push(format!(
+            "version {} to {}: {}",
+            project.version,
+            project.version + 1,
+            migration.description
+        ));
+        project.version += 1;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutPage;
+
+    /// Writes `text` to a project file of the test `name` and loads it.
+    fn load_text(name: &str, text: &str) -> Result<(Project, Vec<String>), ProjectError> {
+        let path = std::env::temp_dir().join(format!(
+            "pdf_coord_picker_{}_{name}.{PROJECT_FILE_EXTENSION}",
+            std::process::id()
+        ));
+        std::fs::write(&path, text).unwrap();
+        let result = load_project(&path);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn unversioned_files_are_upgraded_step_by_step() {
+        let text = r#"(
+            pdf_path: "form.pdf",
+            pdf_hash: "0123456789abcdef",
+            layout: (pages: [(width: 595.0, height: 842.0, fields: [])]),
+        )"#;
+        let (project, report) = load_text("unversioned", text).unwrap();
+        assert_eq!(project.version, PROJECT_FORMAT_VERSION);
+        assert_eq!(report.len(), MIGRATIONS.len());
+        assert!(report[0].starts_with("version 1 to 2: "));
+        assert_eq!(project.pdf_path, PathBuf::from("form.pdf"));
+    }
+
+    #[test]
+    fn fingerprint_is_filled_from_version_2() {
+        let mut project = Project {
+            version: 2,
+            pdf_hash: "0123456789abcdef".to_owned(),
+            layout: Layout {
+                pages: vec![LayoutPage {
+                    width: 595.,
+                    height: 842.,
+                    fields: Vec::new(),
+                }],
+            },
+            ..Default::default()
+        };
+        let report = migrate(&mut project).unwrap();
+        assert_eq!(report.len(), MIGRATIONS.len() - 1);
+        assert!(report[0].starts_with("version 2 to 3: "));
+        assert_eq!(project.fingerprint.content_hash, "0123456789abcdef");
+        assert_eq!(project.fingerprint.page_sizes, vec![[595., 842.]]);
+        assert_eq!(project.version, PROJECT_FORMAT_VERSION);
+    }
+
+    #[test]
+    fn current_files_are_not_changed() {
+        let mut project = Project::default();
+        assert!(migrate(&mut project).unwrap().is_empty());
+        assert_eq!(project, Project::default());
+    }
+
+    #[test]
+    fn newer_files_are_refused() {
+        let newer = PROJECT_FORMAT_VERSION + 1;
+        // fields of the newer format this version does not know
+        let text = format!("(version: {newer}, pages: 3, layers: [])");
+        assert!(matches!(
+            load_text("newer", &text),
+            Err(ProjectError::NewerVersion { version }) if version == newer
+        ));
+        let mut project = Project {
+            version: newer,
+            ..Default::default()
+        };
+        assert!(matches!(
+            migrate(&mut project),
+            Err(ProjectError::NewerVersion { version }) if version == newer
+        ));
+    }
+}
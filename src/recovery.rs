@@ -0,0 +1,231 @@
+// recovery.rs
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::{self, Project, ProjectError};
+
+/// Name the app is started with, its storage directory holds the recovery file.
+pub const APP_ID: &str = "eframe template";
+
+/// Unsaved edits are written at most this often, adding or removing fields writes at once.
+const AUTOSAVE_INTERVAL_SECONDS: f64 = 30.0;
+
+/// Unsaved work, written while editing and removed on save and on a clean shutdown.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Recovery {
+    /// Project file of the work, `None` if it was never saved.
+    pub project_path: Option<PathBuf>,
+    pub project: Project,
+}
+
+fn recovery_file_path() -> Option<PathBuf> {
+    Some(eframe::storage_dir(APP_ID)?.join("recovery.pcp"))
+}
+
+/// Writes to a temporary file first, so a crash while writing keeps the previous recovery.
+fn write_recovery(recovery: &Recovery) -> Result<(), ProjectError> {
+    let path = recovery_file_path().ok_or_else(|| {
+        ProjectError::Format("there is no directory for the recovery file".to_owned())
+    })?;
+    let text = ron::ser::to_string_pretty(recovery, ron::ser::PrettyConfig::default())
+        .map_err(|e| ProjectError::Format(e.to_string()))?;
+    let temporary_path = path.with_extension("pcp.tmp");
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory).map_err(ProjectError::Io)?;
+    }
+    std::fs::write(&temporary_path, text).map_err(ProjectError::Io)?;
+    std::fs::rename(&temporary_path, &path).map_err(ProjectError::Io)
+}
+
+pub fn remove_recovery() {
+    if let Some(path) = recovery_file_path()
+        && let Err(e) = std::fs::remove_file(&path)
+        && e.kind() != std::io::ErrorKind::NotFound
+    {
+        log::error!(
+            "Could not remove recovery file='{}': {e}",
+            path.to_string_lossy()
+        );
+    }
+}
+
+/// The recovery file, unless its project file was saved after it was written.
+pub fn read_recovery() -> Option<Recovery> {
+    let path = recovery_file_path()?;
+    let written = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+    let text = std::fs::read_to_string(&path).ok()?;
+    let mut recovery: Recovery = match ron::from_str(&text) {
+        Ok(recovery) => recovery,
+        Err(e) => {
+            log::error!("Invalid recovery file='{}': {e}", path.to_string_lossy());
+            return None;
+        }
+    };
+    if let Err(e) = project::migrate(&mut recovery.project) {
+        log::error!("Can not recover file='{}': {e}", path.to_string_lossy());
+        return None;
+    }
+    let saved = recovery
+        .project_path
+        .as_ref()
+        .and_then(|project_path| std::fs::metadata(project_path).ok()?.modified().ok());
+    if saved.is_some_and(|saved| saved >= written) {
+        return None;
+    }
+    Some(recovery)
+}
+
+/// What [`Autosave`] does with the recovery file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AutosaveAction {
+    /// The recovery file is up to date.
+    Keep,
+    /// A change is written when the interval passed, in `seconds`.
+    Wait {
+        seconds: f64,
+    },
+    Write,
+    /// There is no unsaved work anymore.
+    Remove,
+}
+
+/// Keeps the recovery file up to date with the unsaved work.
+#[derive(Default)]
+pub struct Autosave {
+    /// Generation and number of fields of the last written project.
+    written: Option<(u64, usize)>,
+    /// Time of the last write in seconds, as given by egui.
+    written_at: f64,
+}
+
+impl Autosave {
+    /// Decides whether the unsaved work is written, which it is if it changed since the last
+    /// write and the change is significant or the interval passed. `unsaved` is the generation
+    /// of the project, which counts its changes, and its number of fields, `None` means there
+    /// are no unsaved changes.
+    ///
+    /// [`AutosaveAction::Write`] and [`AutosaveAction::Remove`] count as done.
+    pub fn next_action(&mut self, now: f64, unsaved: Option<(u64, usize)>) -> AutosaveAction {
+        let Some((generation, field_count)) = unsaved else {
+            return match self.written.take() {
+                Some(_) => AutosaveAction::Remove,
+                None => AutosaveAction::Keep,
+            };
+        };
+        if self
+            .written
+            .is_some_and(|(written_generation, _)| written_generation == generation)
+        {
+            return AutosaveAction::Keep;
+        }
+        let significant = self
+            .written
+            .is_none_or(|(_, written_field_count)| written_field_count != field_count);
+        let due_in = self.written_at + AUTOSAVE_INTERVAL_SECONDS - now;
+        if !significant && due_in > 0.0 {
+            return AutosaveAction::Wait { seconds: due_in };
+        }
+        self.written = Some((generation, field_count));
+        self.written_at = now;
+        AutosaveAction::Write
+    }
+
+    /// Writes or removes the recovery file as [`Self::next_action`] decides, `recovery` is only
+    /// built when a write is due.
+    ///
+    /// Returns the seconds until a pending change is due to be written.
+    pub fn update(
+        &mut self,
+        now: f64,
+        unsaved: Option<(u64, usize)>,
+        recovery: impl FnOnce() -> Option<Recovery>,
+    ) -> Option<f64> {
+        match self.next_action(now, unsaved) {
+            AutosaveAction::Keep => None,
+            AutosaveAction::Wait { seconds } => Some(seconds),
+            AutosaveAction::Write => {
+                if let Some(recovery) = recovery()
+                    && let Err(e) = write_recovery(&recovery)
+                {
+                    log::error!("Could not write recovery file: {e}");
+                }
+                None
+            }
+            AutosaveAction::Remove => {
+                remove_recovery();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_each_generation_once() {
+        let mut autosave = Autosave::default();
+        assert_eq!(autosave.next_action(0.0, None), AutosaveAction::Keep);
+        assert_eq!(
+            autosave.next_action(1.0, Some((1, 3))),
+            AutosaveAction::Write
+        );
+        assert_eq!(
+            autosave.next_action(100.0, Some((1, 3))),
+            AutosaveAction::Keep
+        );
+    }
+
+    #[test]
+    fn edits_wait_for_the_interval() {
+        let mut autosave = Autosave::default();
+        assert_eq!(
+            autosave.next_action(10.0, Some((1, 3))),
+            AutosaveAction::Write
+        );
+        assert_eq!(
+            autosave.next_action(15.0, Some((2, 3))),
+            AutosaveAction::Wait { seconds: 25.0 }
+        );
+        assert_eq!(
+            autosave.next_action(40.0, Some((3, 3))),
+            AutosaveAction::Write
+        );
+    }
+
+    #[test]
+    fn adding_or_removing_fields_writes_at_once() {
+        let mut autosave = Autosave::default();
+        assert_eq!(
+            autosave.next_action(10.0, Some((1, 3))),
+            AutosaveAction::Write
+        );
+        assert_eq!(
+            autosave.next_action(11.0, Some((2, 4))),
+            AutosaveAction::Write
+        );
+        assert_eq!(
+            autosave.next_action(12.0, Some((3, 3))),
+            AutosaveAction::Write
+        );
+    }
+
+    #[test]
+    fn saving_removes_the_written_recovery() {
+        let mut autosave = Autosave::default();
+        assert_eq!(
+            autosave.next_action(10.0, Some((1, 3))),
+            AutosaveAction::Write
+        );
+        assert_eq!(autosave.next_action(11.0, None), AutosaveAction::Remove);
+        assert_eq!(autosave.next_action(12.0, None), AutosaveAction::Keep);
+        // the next change after a save is written at once
+        assert_eq!(
+            autosave.next_action(13.0, Some((2, 3))),
+            AutosaveAction::Write
+        );
+    }
+}
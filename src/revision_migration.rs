@@ -0,0 +1,391 @@
+// revision_migration.rs
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+
+use egui::{Rect, Vec2};
+use pdfium_render::prelude::Pdfium;
+
+use crate::layout::Layout;
+use crate::pdf_load;
+use crate::text_layer::{self, TextWord};
+use crate::units;
+
+/// Words further away from a field than this in points are not used as its anchor.
+const MAX_ANCHOR_DISTANCE: f32 = 100.0;
+
+/// Anchors that moved further than this between revisions are assumed to be a different
+/// occurrence of the text.
+const MAX_SHIFT: f32 = 150.0;
+
+/// Where a proposed position comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShiftSource {
+    /// Word near the field found once on the old and once on the new page.
+    Anchor(String),
+    /// Median shift of the anchored fields on the same page.
+    PageMedian,
+    /// Nothing to go by, the field keeps its position.
+    Unchanged,
+}
+
+/// Proposed new position of one field.
+#[derive(Debug, Clone)]
+pub struct FieldMigration {
+    pub page: usize,
+    /// Index of the field among the fields of its page.
+    pub field: usize,
+    pub unique_id: String,
+    pub shift: Vec2,
+    pub source: ShiftSource,
+    /// Whether the user wants the shift applied.
+    pub accepted: bool,
+}
+
+/// Words occurring exactly once on the page.
+fn unique_words(words: &[TextWord]) -> Vec<&TextWord> {
+    words
+        .iter()
+        .filter(|word| words.iter().filter(|other| other.text == word.text).count() == 1)
+        .collect()
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    values.sort_by(f32::total_cmp);
+    values.get(values.len() / 2).copied()
+}
+
+/// Anchors each field of `layout` to the nearest word which is unique on its page in both
+/// revisions and shifts it like that word moved.
+///
+/// `old_words` and `new_words` are the words of each page of the old and new revision.
+pub fn propose_migration(
+    layout: &Layout,
+    old_words: &[Vec<TextWord>],
+    new_words: &[Vec<TextWord>],
+) -> Vec<FieldMigration> {
+    let mut migrations = Vec::new();
+    for (page, layout_page) in layout.pages.iter().enumerate() {
+        let old_unique = unique_words(old_words.get(page).map_or(&[], Vec::as_slice));
+        let new_unique = unique_words(new_words.get(page).map_or(&[], Vec::as_slice));
+        let first_of_page = migrations.len();
+        for (field, input_field) in layout_page.fields.iter().enumerate() {
+            let rect = Rect::from_min_size(
+                egui::pos2(input_field.pos_x, input_field.pos_y),
+                egui::vec2(input_field.width, input_field.height),
+            );
+            let mut candidates: Vec<(f32, &TextWord)> = old_unique
+                .iter()
+                .map(|word| (text_layer::rect_distance(rect, word.rect), *word))
+                .filter(|(distance, _)| *distance <= MAX_ANCHOR_DISTANCE)
+                .collect();
+            candidates.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            let anchor = candidates.iter().find_map(|(_, old)| {
+                let new = new_unique.iter().find(|new| new.text == old.text)?;
+                let shift = new.rect.min - old.rect.min;
+                (shift.length() <= MAX_SHIFT).then(|| (old.text.clone(), shift))
+            });
+            let (shift, source) = match anchor {
+                Some((text, shift)) => (shift, ShiftSource::Anchor(text)),
+                None => (Vec2::ZERO, ShiftSource::Unchanged),
+            };
+            migrations.push(FieldMigration {
+                page,
+                field,
+                unique_id: input_field.unique_id.clone(),
+                shift,
+                accepted: source != ShiftSource::Unchanged,
+                source,
+            });
+        }
+        // fields without an anchor move like the rest of their page
+        let page_migrations = migrations.get_mut(first_of_page..).unwrap_or_default();
+        let shifts: Vec<Vec2> = page_migrations
+            .iter()
+            .filter(|m| matches!(m.source, ShiftSource::Anchor(_)))
+            .map(|m| m.shift)
+            .collect();
+        let (Some(x), Some(y)) = (
+            median(shifts.iter().map(|shift| shift.x).collect()),
+            median(shifts.iter().map(|shift| shift.y).collect()),
+        ) else {
+            continue;
+        };
+        for migration in page_migrations
+            .iter_mut()
+            .filter(|m| m.source == ShiftSource::Unchanged)
+        {
+            migration.shift = egui::vec2(x, y);
+            migration.source = ShiftSource::PageMedian;
+            migration.accepted = true;
+        }
+    }
+    migrations
+}
+
+/// Copy of `layout` with the accepted shifts applied.
+pub fn apply_migration(layout: &Layout, migrations: &[FieldMigration]) -> Layout {
+    let mut migrated = layout.clone();
+    for migration in migrations.iter().filter(|migration| migration.accepted) {
+        if let Some(field) = migrated
+            .pages
+            .get_mut(migration.page)
+            .and_then(|page| page.fields.get_mut(migration.field))
+        {
+            field.pos_x += migration.shift.x;
+            field.pos_y += migration.shift.y;
+        }
+    }
+    migrated
+}
+
+/// Reads the words of the old and new revision and proposes the field positions.
+pub fn propose_migration_for_files(
+    layout: &Layout,
+    old_path: &Path,
+    new_path: &Path,
+) -> Result<Vec<FieldMigration>, String> {
+    let pdfium = Pdfium::default();
+    let words = |path: &Path| {
+        pdf_load::load_pdf_native(&pdfium, path)
+            .and_then(|document| text_layer::document_words(&document))
+            .map_err(|e| format!("Could not read file='{}': {e}", path.to_string_lossy()))
+    };
+    let old_words = words(old_path)?;
+    let new_words = words(new_path)?;
+    Ok(propose_migration(layout, &old_words, &new_words))
+}
+
+/// Review of the proposed positions before they are applied to the new revision.
+#[derive(Default)]
+pub struct RevisionMigrationWindow {
+    pub open: bool,
+    new_pdf_path: Option<PathBuf>,
+    /// Layout the proposal was made for, its field indices stay valid while the window is open.
+    layout: Layout,
+    receiver: Option<mpsc::Receiver<Result<Vec<FieldMigration>, String>>>,
+    proposal: Option<Result<Vec<FieldMigration>, String>>,
+}
+
+impl RevisionMigrationWindow {
+    /// Opens the window and proposes how to move `layout` of `old_path` to `new_path` on
+    /// another thread.
+    pub fn start(&mut self, layout: Layout, old_path: PathBuf, new_path: PathBuf) {
+        let (producer, receiver) = mpsc::channel();
+        let thread_layout = layout.clone();
+        let thread_new_path = new_path.clone();
+        std::thread::spawn(move || {
+            producer
+                .send(propose_migration_for_files(
+                    &thread_layout,
+                    &old_path,
+                    &thread_new_path,
+                ))
+                .ok();
+        });
+        self.receiver = Some(receiver);
+        self.proposal = None;
+        self.layout = layout;
+        self.new_pdf_path = Some(new_path);
+        self.open = true;
+    }
+
+    /// Takes the proposal of the migration thread, returns whether it is still running.
+    fn poll(&mut self) -> bool {
+        let Some(receiver) = &self.receiver else {
+            return false;
+        };
+        self.proposal = match receiver.try_recv() {
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => Some(Err(
+                "Error: Connection to the revision migration was lost.".to_owned(),
+            )),
+            Ok(proposal) => Some(proposal),
+        };
+        self.receiver = None;
+        false
+    }
+
+    /// Shows the window, returns the new pdf and the migrated layout when the user applies it.
+    ///
+    /// The migration applies to the layout as it was when the window was opened.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<(PathBuf, Layout)> {
+        let mut open = self.open;
+        let mut apply = false;
+        egui::Window::new("Migrate to new revision")
+            .open(&mut open)
+            .default_width(450.)
+            .show(ctx, |ui| {
+                if let Some(path) = &self.new_pdf_path {
+                    ui.label(format!("new revision: {}", path.to_string_lossy()));
+                }
+                if self.poll() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Reading the text of both revisions…");
+                    });
+                    return;
+                }
+                match &mut self.proposal {
+                    Some(Ok(migrations)) => {
+                        ui_migrations(ui, migrations);
+                        ui.separator();
+                        apply = ui
+                            .button("Open new revision with these positions")
+                            .clicked();
+                    }
+                    Some(Err(e)) => {
+                        ui.label(e.as_str());
+                    }
+                    None => {}
+                }
+            });
+        self.open = open && !apply;
+        if !apply {
+            return None;
+        }
+        let Some(Ok(migrations)) = self.proposal.take() else {
+            return None;
+        };
+        let migrated = apply_migration(&std::mem::take(&mut self.layout), &migrations);
+        Some((self.new_pdf_path.take()?, migrated))
+    }
+}
+
+fn ui_migrations(ui: &mut egui::Ui, migrations: &mut [FieldMigration]) {
+    let anchored = migrations
+        .iter()
+        .filter(|m| matches!(m.source, ShiftSource::Anchor(_)))
+        .count();
+    ui.label(format!(
+        "{anchored} of {} fields were anchored to text, shifts in mm:",
+        migrations.len()
+    ));
+    egui::ScrollArea::vertical()
+        .max_height(400.0)
+        .show(ui, |ui| {
+            egui::Grid::new("revision_migration_fields")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("apply");
+                    ui.strong("page");
+                    ui.strong("field");
+                    ui.strong("x");
+                    ui.strong("y");
+                    ui.strong("anchor");
+                    ui.end_row();
+                    for migration in migrations {
+                        ui.checkbox(&mut migration.accepted, "");
+                        ui.label((migration.page + 1).to_string());
+                        ui.label(&migration.unique_id);
+                        ui.label(format!("{:+.1}", units::pt_to_mm(migration.shift.x)));
+                        ui.label(format!("{:+.1}", units::pt_to_mm(migration.shift.y)));
+                        ui.label(match &migration.source {
+                            ShiftSource::Anchor(text) => format!("'{text}'"),
+                            ShiftSource::PageMedian => "page median".to_owned(),
+                            ShiftSource::Unchanged => "none".to_owned(),
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutPage;
+    use crate::pdf_text_input::PdfInputFieldSerde;
+
+    fn word(text: &str, x: f32, y: f32) -> TextWord {
+        TextWord {
+            text: text.to_owned(),
+            rect: Rect::from_min_size(egui::pos2(x, y), egui::vec2(30.0, 10.0)),
+        }
+    }
+
+    fn layout(fields: &[(&str, f32, f32)]) -> Layout {
+        Layout {
+            pages: vec![LayoutPage {
+                width: 595.,
+                height: 842.,
+                fields: fields
+                    .iter()
+                    .map(|&(unique_id, pos_x, pos_y)| PdfInputFieldSerde {
+                        unique_id: unique_id.to_owned(),
+                        pos_x,
+                        pos_y,
+                        width: 100.,
+                        height: 12.,
+                        ..Default::default()
+                    })
+                    .collect(),
+            }],
+        }
+    }
+
+    #[test]
+    fn fields_move_with_their_anchor() {
+        let layout = layout(&[("name", 50., 10.)]);
+        let old_words = vec![vec![word("Name", 10., 10.), word("Date", 10., 200.)]];
+        let new_words = vec![vec![word("Name", 15., 30.), word("Date", 10., 200.)]];
+        let migrations = propose_migration(&layout, &old_words, &new_words);
+        assert_eq!(migrations.len(), 1);
+        assert_eq!(migrations[0].source, ShiftSource::Anchor("Name".to_owned()));
+        assert_eq!(migrations[0].shift, egui::vec2(5., 20.));
+        assert!(migrations[0].accepted);
+    }
+
+    #[test]
+    fn anchors_moving_too_far_are_rejected() {
+        let layout = layout(&[("name", 50., 10.)]);
+        let old_words = vec![vec![word("Name", 10., 10.)]];
+        let new_words = vec![vec![word("Name", 10., 10. + MAX_SHIFT + 1.)]];
+        let migrations = propose_migration(&layout, &old_words, &new_words);
+        assert_eq!(migrations[0].source, ShiftSource::Unchanged);
+        assert_eq!(migrations[0].shift, Vec2::ZERO);
+        assert!(!migrations[0].accepted);
+    }
+
+    #[test]
+    fn unanchored_fields_move_like_their_page() {
+        // "Remarks" occurs twice on the new page and can not anchor the second field
+        let layout = layout(&[
+            ("name", 50., 10.),
+            ("city", 50., 40.),
+            ("remarks", 50., 500.),
+        ]);
+        let old_words = vec![vec![
+            word("Name", 10., 10.),
+            word("City", 10., 40.),
+            word("Remarks", 10., 500.),
+        ]];
+        let new_words = vec![vec![
+            word("Name", 10., 20.),
+            word("City", 10., 50.),
+            word("Remarks", 10., 510.),
+            word("Remarks", 10., 700.),
+        ]];
+        let migrations = propose_migration(&layout, &old_words, &new_words);
+        assert_eq!(migrations[2].source, ShiftSource::PageMedian);
+        assert_eq!(migrations[2].shift, egui::vec2(0., 10.));
+        assert!(migrations[2].accepted);
+    }
+
+    #[test]
+    fn only_accepted_shifts_are_applied() {
+        let layout = layout(&[("name", 50., 10.), ("city", 50., 40.)]);
+        let migration = |field: usize, accepted: bool| FieldMigration {
+            page: 0,
+            field,
+            unique_id: String::new(),
+            shift: egui::vec2(5., 20.),
+            source: ShiftSource::PageMedian,
+            accepted,
+        };
+        let migrated = apply_migration(&layout, &[migration(0, true), migration(1, false)]);
+        let fields = &migrated.pages[0].fields;
+        assert_eq!((fields[0].pos_x, fields[0].pos_y), (55., 30.));
+        assert_eq!((fields[1].pos_x, fields[1].pos_y), (50., 40.));
+    }
+}
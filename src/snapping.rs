@@ -0,0 +1,230 @@
+// snapping.rs
+
+use egui::{Pos2, Rangef, pos2};
+use pdfium_render::prelude::{
+    PdfDocument, PdfMatrix, PdfPage, PdfPageObject, PdfPageObjectCommon as _,
+    PdfPageObjectsCommon as _, PdfPathSegmentType, PdfPathSegments as _, PdfPoints, PdfiumError,
+};
+
+/// Field edges closer than this in points to a guide snap to it.
+pub const SNAP_DISTANCE: f32 = 5.0;
+
+/// Path segments shorter than this in points, e.g. parts of curves or text, are no guides.
+const MIN_LINE_LENGTH: f32 = 4.0;
+
+/// Lines deviating less than this in points from horizontal or vertical count as straight.
+const STRAIGHT_TOLERANCE: f32 = 0.5;
+
+/// Horizontal or vertical line a field edge can snap to, in points from the top left corner of
+/// the page.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapLine {
+    pub from: Pos2,
+    pub to: Pos2,
+}
+
+/// Straight lines of box borders and text baselines of one page.
+#[derive(Debug, Clone, Default)]
+pub struct SnapGuides {
+    /// Straight path segments, the borders of boxes.
+    pub horizontal: Vec<SnapLine>,
+    pub vertical: Vec<SnapLine>,
+    pub baselines: Vec<SnapLine>,
+}
+
+impl SnapGuides {
+    /// Reads the path segments and the baselines of the text objects of `page`.
+    pub fn from_page(page: &PdfPage<'_>) -> Result<Self, PdfiumError> {
+        let mut guides = Self::default();
+        let page_height = page.height().value;
+        for object in page.objects().iter() {
+            guides.add_object(&object, PdfMatrix::IDENTITY, page_height)?;
+        }
+        Ok(guides)
+    }
+
+    /// Adds the guides of `object`, whose coordinates are transformed by `parent` into page space.
+    fn add_object(
+        &mut self,
+        object: &PdfPageObject<'_>,
+        parent: PdfMatrix,
+        page_height: f32,
+    ) -> Result<(), PdfiumError> {
+        let to_page = |matrix: PdfMatrix, x, y| {
+            let (x, y) = matrix.apply_to_points(x, y);
+            pos2(x.value, page_height - y.value)
+        };
+        match object {
+            PdfPageObject::Path(path) => {
+                let matrix = path.matrix()?.multiply(parent);
+                let mut subpath_start = None;
+                let mut previous = None;
+                for segment in path.segments().iter() {
+                    let point = to_page(matrix, segment.x(), segment.y());
+                    match segment.segment_type() {
+                        PdfPathSegmentType::MoveTo => subpath_start = Some(point),
+                        PdfPathSegmentType::LineTo => {
+                            if let Some(previous) = previous {
+                                self.add_line(previous, point);
+                            }
+                        }
+                        PdfPathSegmentType::BezierTo | PdfPathSegmentType::Unknown => {}
+                    }
+                    if segment.is_close()
+                        && let Some(start) = subpath_start
+                    {
+                        self.add_line(point, start);
+                    }
+                    previous = Some(point);
+                }
+            }
+            PdfPageObject::Text(text) => {
+                // the bounds include descenders, the baseline runs through the origin of the
+                // text matrix
+                let origin = to_page(
+                    text.matrix()?.multiply(parent),
+                    PdfPoints::ZERO,
+                    PdfPoints::ZERO,
+                );
+                let bounds = text.bounds()?;
+                let left = to_page(parent, bounds.left(), bounds.bottom());
+                let right = to_page(parent, bounds.right(), bounds.bottom());
+                self.baselines.push(SnapLine {
+                    from: pos2(left.x.min(right.x), origin.y),
+                    to: pos2(left.x.max(right.x), origin.y),
+                });
+            }
+            PdfPageObject::XObjectForm(form) => {
+                let matrix = form.matrix()?.multiply(parent);
+                for index in form.as_range() {
+                    self.add_object(&form.get(index)?, matrix, page_height)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Adds the segment from `a` to `b` if it is horizontal or vertical.
+    pub fn add_line(&mut self, a: Pos2, b: Pos2) {
+        let delta = b - a;
+        if delta.y.abs() <= STRAIGHT_TOLERANCE && delta.x.abs() >= MIN_LINE_LENGTH {
+            let y = f32::midpoint(a.y, b.y);
+            self.horizontal.push(SnapLine {
+                from: pos2(a.x.min(b.x), y),
+                to: pos2(a.x.max(b.x), y),
+            });
+        } else if delta.x.abs() <= STRAIGHT_TOLERANCE && delta.y.abs() >= MIN_LINE_LENGTH {
+            let x = f32::midpoint(a.x, b.x);
+            self.vertical.push(SnapLine {
+                from: pos2(x, a.y.min(b.y)),
+                to: pos2(x, a.y.max(b.y)),
+            });
+        }
+    }
+
+    /// Nearest horizontal line or baseline within [`SNAP_DISTANCE`] of `y` which reaches into
+    /// `x_range`.
+    pub fn snap_y(&self, y: f32, x_range: Rangef) -> Option<SnapLine> {
+        let lines = self.horizontal.iter().chain(&self.baselines);
+        nearest(lines, y, x_range, |point| (point.y, point.x))
+    }
+
+    /// Nearest vertical line within [`SNAP_DISTANCE`] of `x` which reaches into `y_range`.
+    pub fn snap_x(&self, x: f32, y_range: Rangef) -> Option<SnapLine> {
+        nearest(self.vertical.iter(), x, y_range, |point| (point.x, point.y))
+    }
+}
+
+/// `split` returns a point's coordinate across and along the lines.
+fn nearest<'a>(
+    lines: impl Iterator<Item = &'a SnapLine>,
+    position: f32,
+    range: Rangef,
+    split: impl Fn(Pos2) -> (f32, f32),
+) -> Option<SnapLine> {
+    let range = range.expand(SNAP_DISTANCE);
+    lines
+        .filter(|line| {
+            let (_, start) = split(line.from);
+            let (_, end) = split(line.to);
+            range.intersects(Rangef::new(start, end))
+        })
+        .map(|line| ((split(line.from).0 - position).abs(), line))
+        .filter(|(distance, _)| *distance <= SNAP_DISTANCE)
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, line)| *line)
+}
+
+/// Snap guides of every page of `document`, indexed like the pages.
+pub fn document_guides(document: &PdfDocument<'_>) -> Result<Vec<SnapGuides>, PdfiumError> {
+    document
+        .pages()
+        .iter()
+        .map(|page| SnapGuides::from_page(&page))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_long_straight_segments_are_added() {
+        let mut guides = SnapGuides::default();
+        guides.add_line(pos2(100., 50.4), pos2(10., 50.));
+        guides.add_line(pos2(20., 10.), pos2(20.3, 90.));
+        // slanted
+        guides.add_line(pos2(10., 10.), pos2(90., 11.));
+        // too short
+        guides.add_line(pos2(10., 10.), pos2(13., 10.));
+        assert_eq!(guides.horizontal.len(), 1);
+        assert_eq!(guides.horizontal[0].from, pos2(10., 50.2));
+        assert_eq!(guides.horizontal[0].to, pos2(100., 50.2));
+        assert_eq!(guides.vertical.len(), 1);
+        assert_eq!(guides.vertical[0].from, pos2(20.15, 10.));
+        assert_eq!(guides.vertical[0].to, pos2(20.15, 90.));
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_line() {
+        let mut guides = SnapGuides::default();
+        guides.add_line(pos2(10., 50.), pos2(100., 50.));
+        guides.add_line(pos2(10., 53.), pos2(100., 53.));
+        guides.baselines.push(SnapLine {
+            from: pos2(10., 56.),
+            to: pos2(100., 56.),
+        });
+        let x_range = Rangef::new(20., 80.);
+        assert_eq!(
+            guides.snap_y(52., x_range).map(|line| line.from.y),
+            Some(53.)
+        );
+        assert_eq!(
+            guides.snap_y(57., x_range).map(|line| line.from.y),
+            Some(56.)
+        );
+        assert!(guides.snap_y(40., x_range).is_none());
+    }
+
+    #[test]
+    fn ignores_lines_beside_the_field() {
+        let mut guides = SnapGuides::default();
+        guides.add_line(pos2(200., 10.), pos2(200., 100.));
+        // ends just within the snap distance above the field
+        guides.add_line(pos2(203., 200.), pos2(203., 296.));
+        assert_eq!(
+            guides
+                .snap_x(201., Rangef::new(20., 80.))
+                .map(|line| line.from.x),
+            Some(200.)
+        );
+        assert_eq!(
+            guides
+                .snap_x(201., Rangef::new(300., 400.))
+                .map(|line| line.from.x),
+            Some(203.)
+        );
+        assert!(guides.snap_x(201., Rangef::new(120., 180.)).is_none());
+    }
+}
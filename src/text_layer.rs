@@ -0,0 +1,67 @@
+// text_layer.rs
+
+use egui::{Rect, pos2};
+use pdfium_render::prelude::{PdfDocument, PdfPage, PdfiumError};
+
+/// Word of a page's text layer.
+#[derive(Debug, Clone)]
+pub struct TextWord {
+    pub text: String,
+    /// Bounds in pdf points from the top left corner of the page, like field rects.
+    pub rect: Rect,
+}
+
+/// Words of every page of `document`, indexed like the pages.
+pub fn document_words(document: &PdfDocument<'_>) -> Result<Vec<Vec<TextWord>>, PdfiumError> {
+    document
+        .pages()
+        .iter()
+        .map(|page| page_words(&page))
+        .collect()
+}
+
+/// Splits the characters of the page into words at whitespace, gaps and line changes.
+pub fn page_words(page: &PdfPage<'_>) -> Result<Vec<TextWord>, PdfiumError> {
+    let page_height = page.height().value;
+    let text = page.text()?;
+    let mut words = Vec::new();
+    let mut current: Option<TextWord> = None;
+    for char in text.chars().iter() {
+        let Some(c) = char.unicode_char() else {
+            continue;
+        };
+        if c.is_whitespace() || c.is_control() {
+            words.extend(current.take());
+            continue;
+        }
+        let bounds = char.tight_bounds()?;
+        let rect = Rect::from_min_max(
+            pos2(bounds.left().value, page_height - bounds.top().value),
+            pos2(bounds.right().value, page_height - bounds.bottom().value),
+        );
+        if let Some(word) = &mut current {
+            let line_height = word.rect.height().max(rect.height());
+            let same_line = (rect.center().y - word.rect.center().y).abs() < line_height / 2.0;
+            let gap = rect.left() - word.rect.right();
+            if same_line && gap > -line_height && gap < line_height * 0.3 {
+                word.text.push(c);
+                word.rect = word.rect.union(rect);
+                continue;
+            }
+            words.extend(current.take());
+        }
+        current = Some(TextWord {
+            text: c.to_string(),
+            rect,
+        });
+    }
+    words.extend(current);
+    Ok(words)
+}
+
+/// Distance between two rects, 0 if they overlap.
+pub fn rect_distance(a: Rect, b: Rect) -> f32 {
+    let dx = (a.left() - b.right()).max(b.left() - a.right()).max(0.0);
+    let dy = (a.top() - b.bottom()).max(b.top() - a.bottom()).max(0.0);
+    dx.hypot(dy)
+}
@@ -0,0 +1,78 @@
+// units.rs
+
+use serde::{Deserialize, Serialize};
+
+pub const POINTS_PER_INCH: f32 = 72.0;
+pub const MILLIMETERS_PER_INCH: f32 = 25.4;
+
+pub fn mm_to_pt(mm: f32) -> f32 {
+    mm / MILLIMETERS_PER_INCH * POINTS_PER_INCH
+}
+
+pub fn pt_to_mm(pt: f32) -> f32 {
+    pt / POINTS_PER_INCH * MILLIMETERS_PER_INCH
+}
+
+/// Unit of the coordinates shown to the user.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CoordinateUnit {
+    #[default]
+    Points,
+    Millimeters,
+}
+
+impl CoordinateUnit {
+    /// Converts `pt` pdf points to this unit.
+    pub fn convert_points(self, pt: f32) -> f32 {
+        match self {
+            Self::Points => pt,
+            Self::Millimeters => pt_to_mm(pt),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Points => "pt",
+            Self::Millimeters => "mm",
+        }
+    }
+}
+
+/// Corner of the page the shown coordinates are measured from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CoordinateOrigin {
+    #[default]
+    TopLeft,
+    /// The pdf convention.
+    BottomLeft,
+}
+
+impl CoordinateOrigin {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TopLeft => "top left",
+            Self::BottomLeft => "bottom left",
+        }
+    }
+}
+
+pub fn coordinate_settings_ui(
+    ui: &mut egui::Ui,
+    unit: &mut CoordinateUnit,
+    origin: &mut CoordinateOrigin,
+) {
+    egui::ComboBox::from_label("unit")
+        .selected_text(unit.label())
+        .show_ui(ui, |ui| {
+            for value in [CoordinateUnit::Points, CoordinateUnit::Millimeters] {
+                ui.selectable_value(unit, value, value.label());
+            }
+        });
+    egui::ComboBox::from_label("origin")
+        .selected_text(origin.label())
+        .show_ui(ui, |ui| {
+            for value in [CoordinateOrigin::TopLeft, CoordinateOrigin::BottomLeft] {
+                ui.selectable_value(origin, value, value.label());
+            }
+        });
+}